@@ -1,93 +1,448 @@
 use std::collections::HashMap;
+use std::fmt;
+use std::time::{Duration, Instant};
 
-use crate::board::{self, Board};
+use crate::board::{self, Board, Move};
 use crate::player::Player;
 use crate::{game, hash};
 use anyhow::{anyhow, Result};
+use rand::distributions::{Distribution, WeightedIndex};
 use rand::Rng;
 
-pub fn random_ai(board: &Board, player: &Player) -> Result<Board> {
+/// Minimum score of a win for X (and maximum score of a win for O, negated)
+pub const WIN_SCORE: i8 = 10;
+
+/// Move chosen by an engine. Engines able to evaluate the position also give the score
+/// (from X point of view) and the principal variation starting with this move.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EngineMove {
+    /// case played
+    #[cfg_attr(feature = "serde", serde(with = "crate::board::serde_move"))]
+    pub coord: Move,
+    /// minimax score from X point of view, None for the engines without evaluation
+    pub score: Option<i8>,
+    /// principal variation, starting with `coord`
+    #[cfg_attr(feature = "serde", serde(with = "crate::board::serde_moves"))]
+    pub pv: Vec<Move>,
+}
+
+impl From<Move> for EngineMove {
+    fn from(coord: Move) -> Self {
+        EngineMove {
+            coord,
+            score: None,
+            pv: vec![coord],
+        }
+    }
+}
+
+/// Limits given to a search engine to choose one move
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct SearchLimits {
+    /// time when the search must return its best move so far
+    pub deadline: Option<Instant>,
+    /// maximum number of positions expanded
+    pub nodes: Option<u64>,
+}
+
+impl SearchLimits {
+    /// Limits of a move played in `budget`, without limit if None
+    pub fn from_budget(budget: Option<Duration>) -> SearchLimits {
+        SearchLimits {
+            deadline: budget.map(|b| Instant::now() + b),
+            nodes: None,
+        }
+    }
+
+    fn is_time_over(&self) -> bool {
+        self.deadline.is_some_and(|d| Instant::now() >= d)
+    }
+}
+
+/// Cases by preference of `TieBreak::Preferred` : center, then corners, then sides
+const PREFERRED_ORDER: [Move; 9] = [
+    (1, 1),
+    (0, 0),
+    (0, 2),
+    (2, 0),
+    (2, 2),
+    (0, 1),
+    (1, 0),
+    (1, 2),
+    (2, 1),
+];
+
+/// How minimax chooses between moves with the same score
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum TieBreak {
+    /// first move in (y,x) order
+    #[default]
+    First,
+    /// random move
+    Random,
+    /// center, then corners, then sides
+    Preferred,
+}
+
+/// Engine playing a random legal move
+pub fn random_ai(board: &Board) -> Result<EngineMove> {
     let legal_moves = find_all_legal_moves(board);
-    let new_board = match select_one_random_move(&legal_moves, board, player) {
-        Some(b) => b,
-        None => return Err(anyhow!("no legal move available")),
-    };
-    Ok(new_board)
+    match select_one_random_move(&legal_moves) {
+        Some(m) => Ok(m.into()),
+        None => Err(anyhow!("no legal move available")),
+    }
 }
 
-pub fn finds_winning_moves_ai(board: &Board, player: &Player) -> Result<Board> {
+/// Engine playing a winning move if there is one, a random move otherwise
+pub fn finds_winning_moves_ai(board: &Board, player: &Player) -> Result<EngineMove> {
     let legal_moves = find_all_legal_moves(board);
 
-    if let Some(b) = find_a_winning_move(&legal_moves, board, player) {
-        return Ok(b);
+    if let Some(m) = find_a_winning_move(&legal_moves, board, player) {
+        return Ok(m.into());
     }
 
-    let new_board = match select_one_random_move(&legal_moves, board, player) {
-        Some(b) => b,
-        None => return Err(anyhow!("no legal move available")),
-    };
-    Ok(new_board)
+    match select_one_random_move(&legal_moves) {
+        Some(m) => Ok(m.into()),
+        None => Err(anyhow!("no legal move available")),
+    }
 }
 
-pub fn finds_winning_and_not_losing_moves_ai(board: &Board, player: &Player) -> Result<Board> {
+/// Engine playing a winning move if there is one, else blocking a winning move of the
+/// opponent, else a random move
+pub fn finds_winning_and_not_losing_moves_ai(board: &Board, player: &Player) -> Result<EngineMove> {
     let legal_moves = find_all_legal_moves(board);
 
-    if let Some(b) = find_a_winning_move(&legal_moves, board, player) {
-        return Ok(b);
+    if let Some(m) = find_a_winning_move(&legal_moves, board, player) {
+        return Ok(m.into());
     }
 
-    if let Some(b) = find_a_blocking_move(&legal_moves, board, player) {
-        return Ok(b);
+    if let Some(m) = find_a_blocking_move(&legal_moves, board, player) {
+        return Ok(m.into());
     }
 
-    let new_board = match select_one_random_move(&legal_moves, board, player) {
-        Some(b) => b,
-        None => return Err(anyhow!("no legal move available")),
-    };
-    Ok(new_board)
+    match select_one_random_move(&legal_moves) {
+        Some(m) => Ok(m.into()),
+        None => Err(anyhow!("no legal move available")),
+    }
 }
 
+/// Minimax driven by iterative deepening : the search is run deeper and deeper until the
+/// tree is fully solved, and the best move of the last completed iteration is returned if
+/// the time or node budget runs out.
 pub fn minimax_algo_ai(
     board: &Board,
     player: &Player,
     cache: &mut HashMap<u64, i8>,
-) -> Result<Board> {
+    limits: &SearchLimits,
+    tie_break: TieBreak,
+) -> Result<EngineMove> {
     let legal_moves = find_all_legal_moves(board);
-    let mut scores: Vec<(i8, Board)> = Vec::new();
-    for m in legal_moves {
-        let new_board = board::make_move(board, m, player).unwrap();
-        let score = minimax_score(&new_board, player, cache);
-        scores.push((score, new_board));
+    if legal_moves.is_empty() {
+        return Err(anyhow!("no legal move available in minimax_algo_ai"));
     }
-    //log::debug!("{:?}", scores);
 
-    if scores.is_empty() {
-        return Err(anyhow!("no legal move available in minimax_algo_ai"));
+    let mut search = Search {
+        cache,
+        estimates: HashMap::new(),
+        limits,
+        nodes: 0,
+    };
+    let (scores, solved) = search.deepening_scores(board, player);
+    let best = break_tie(&scores, player, tie_break);
+
+    let mut pv = vec![best.1];
+    if solved {
+        let new_board = board::make_move(board, best.1, player).unwrap();
+        pv.extend(principal_variation(&new_board, player, search.cache));
     }
+    Ok(EngineMove {
+        coord: best.1,
+        score: Some(best.0),
+        pv,
+    })
+}
 
-    let mut board = *board;
-    match player {
-        Player::PlayerX => {
-            let mut score: i8 = -10;
-            for s in scores {
-                if s.0 > score {
-                    score = s.0;
-                    board = s.1;
-                }
+/// Return the best scored move, choosing between equal scores with `tie_break`
+fn break_tie(scores: &[(i8, Move)], player: &Player, tie_break: TieBreak) -> (i8, Move) {
+    let mut best_score = scores[0].0;
+    for s in scores {
+        if is_better_for(player, s.0, best_score) {
+            best_score = s.0;
+        }
+    }
+    let mut best_moves: Vec<(i8, Move)> = scores
+        .iter()
+        .filter(|s| s.0 == best_score)
+        .copied()
+        .collect();
+    best_moves.sort_by_key(|s| s.1);
+    match tie_break {
+        TieBreak::First => best_moves[0],
+        TieBreak::Random => best_moves[rand::thread_rng().gen_range(0..best_moves.len())],
+        TieBreak::Preferred => *best_moves
+            .iter()
+            .min_by_key(|s| PREFERRED_ORDER.iter().position(|m| *m == s.1))
+            .unwrap(),
+    }
+}
+
+/// Game-theoretic value of a move for the player making it, with the number of moves
+/// (of both players, this one included) until the end of the game
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Outcome {
+    /// won in this number of moves
+    Win(usize),
+    /// drawn
+    Draw,
+    /// lost in this number of moves
+    Loss(usize),
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let moves = |n: &usize| if *n == 1 { "move" } else { "moves" };
+        match self {
+            Outcome::Win(n) => write!(f, "win in {} {}", n, moves(n)),
+            Outcome::Draw => write!(f, "draw"),
+            Outcome::Loss(n) => write!(f, "loss in {} {}", n, moves(n)),
+        }
+    }
+}
+
+/// Return the outcome of a move of `player` scored `score` by minimax, played on a board
+/// with `empty_cases` empty cases
+pub fn outcome(score: i8, player: &Player, empty_cases: usize) -> Outcome {
+    if score == 0 {
+        return Outcome::Draw;
+    }
+    let moves = empty_cases - (score.unsigned_abs() - WIN_SCORE as u8) as usize;
+    match (player, score > 0) {
+        (Player::PlayerX, true) | (Player::PlayerO, false) => Outcome::Win(moves),
+        _ => Outcome::Loss(moves),
+    }
+}
+
+/// Engine of configurable strength : plays the minimax best move with probability
+/// level / 10, otherwise a worse move, better moves being more likely. The scores are the
+/// ones of the deepest iteration completed within `limits`.
+pub fn level_ai(
+    board: &Board,
+    player: &Player,
+    level: u8,
+    cache: &mut HashMap<u64, i8>,
+    limits: &SearchLimits,
+) -> Result<EngineMove> {
+    if find_all_legal_moves(board).is_empty() {
+        return Err(anyhow!("no legal move available in level_ai"));
+    }
+    let mut search = Search {
+        cache,
+        estimates: HashMap::new(),
+        limits,
+        nodes: 0,
+    };
+    let (scores, _) = search.deepening_scores(board, player);
+    let best_probability = f64::from(level.clamp(1, 10)) / 10.0;
+    let (score, m) = pick_move_with_blunders(&scores, player, best_probability);
+    Ok(EngineMove {
+        coord: m,
+        score: Some(score),
+        pv: vec![m],
+    })
+}
+
+/// Return the minimax score of every legal move of `player`
+pub fn minimax_scores(
+    board: &Board,
+    player: &Player,
+    cache: &mut HashMap<u64, i8>,
+) -> Vec<(i8, Move)> {
+    find_all_legal_moves(board)
+        .into_iter()
+        .map(|m| {
+            let new_board = board::make_move(board, m, player).unwrap();
+            (minimax_score(&new_board, player, cache), m)
+        })
+        .collect()
+}
+
+fn pick_move_with_blunders(
+    scores: &[(i8, Move)],
+    player: &Player,
+    best_probability: f64,
+) -> (i8, Move) {
+    let mut best = scores[0];
+    for s in scores {
+        if is_better_for(player, s.0, best.0) {
+            best = *s;
+        }
+    }
+    let suboptimal: Vec<(i8, Move)> = scores
+        .iter()
+        .filter(|s| is_better_for(player, best.0, s.0))
+        .copied()
+        .collect();
+
+    let mut rng = rand::thread_rng();
+    if suboptimal.is_empty() || rng.gen_bool(best_probability) {
+        return best;
+    }
+    // score seen by the player, shifted to be a positive weight
+    let max_weight = 2 * i32::from(WIN_SCORE) + 10;
+    let weights = suboptimal.iter().map(|s| match player {
+        Player::PlayerX => i32::from(s.0) + max_weight,
+        Player::PlayerO => max_weight - i32::from(s.0),
+    });
+    let index = WeightedIndex::new(weights).unwrap();
+    suboptimal[index.sample(&mut rng)]
+}
+
+/// State of a depth limited minimax search
+struct Search<'a> {
+    /// exact scores, shared between moves and games
+    cache: &'a mut HashMap<u64, i8>,
+    /// scores (and depth searched) found by depth limited searches, used to search the
+    /// best moves first in the next iterations
+    estimates: HashMap<u64, (i8, usize)>,
+    limits: &'a SearchLimits,
+    nodes: u64,
+}
+
+impl Search<'_> {
+    fn is_over(&self) -> bool {
+        self.limits.is_time_over() || self.limits.nodes.is_some_and(|n| self.nodes >= n)
+    }
+
+    /// Score of `board` after `player` moved, searching `depth` moves ahead, and whether
+    /// this score is exact. None if the budget ran out.
+    fn score(&mut self, board: &Board, player: &Player, depth: usize) -> Option<(i8, bool)> {
+        let key = hash::compute_cache(board, player);
+        if let Some(score) = self.cache.get(&key) {
+            return Some((*score, true));
+        }
+        if let Some(score) = minimax_score_win_or_draw(board) {
+            self.cache.insert(key, score);
+            return Some((score, true));
+        }
+        if depth == 0 {
+            return Some((0, false));
+        }
+        if let Some((score, d)) = self.estimates.get(&key) {
+            if *d >= depth {
+                return Some((*score, false));
             }
-            Ok(board)
         }
-        Player::PlayerO => {
-            let mut score: i8 = 10;
-            for s in scores {
-                if s.0 < score {
-                    score = s.0;
-                    board = s.1;
-                }
+        self.nodes += 1;
+        if self.is_over() {
+            return None;
+        }
+
+        let opponent = game::switch_player(player);
+        let mut best: Option<i8> = None;
+        let mut exact = true;
+        for m in self.ordered_moves(board, &opponent) {
+            let new_board = board::make_move(board, m, &opponent).unwrap();
+            let (score, e) = self.score(&new_board, &opponent, depth - 1)?;
+            exact &= e;
+            if best.is_none_or(|b| is_better_for(&opponent, score, b)) {
+                best = Some(score);
+            }
+        }
+        let best = best.unwrap();
+        if exact {
+            self.cache.insert(key, best);
+        } else {
+            self.estimates.insert(key, (best, depth));
+        }
+        Some((best, exact))
+    }
+
+    /// Iterative deepening : score every legal move of `player` deeper and deeper until the
+    /// tree is fully solved or the budget runs out. Return the scores of the deepest completed
+    /// iteration (all 0 if none) and whether they are exact.
+    fn deepening_scores(&mut self, board: &Board, player: &Player) -> (Vec<(i8, Move)>, bool) {
+        let legal_moves = find_all_legal_moves(board);
+        let mut completed: Vec<(i8, Move)> = legal_moves.iter().map(|m| (0, *m)).collect();
+        for depth in 1..=legal_moves.len() {
+            let mut scores: Vec<(i8, Move)> = Vec::new();
+            let mut exact = true;
+            for m in self.ordered_moves(board, player) {
+                let new_board = board::make_move(board, m, player).unwrap();
+                let Some((score, e)) = self.score(&new_board, player, depth - 1) else {
+                    log::debug!("search stopped during depth {}", depth);
+                    return (completed, false);
+                };
+                exact &= e;
+                scores.push((score, m));
+            }
+            log::debug!("depth {} : scores {:?}", depth, scores);
+            completed = scores;
+            if exact {
+                return (completed, true);
+            }
+        }
+        (completed, false)
+    }
+
+    /// Legal moves of `player`, best known first
+    fn ordered_moves(&self, board: &Board, player: &Player) -> Vec<Move> {
+        let mut moves: Vec<(i8, Move)> = find_all_legal_moves(board)
+            .into_iter()
+            .map(|m| {
+                let key = hash::compute_cache(&board::make_move(board, m, player).unwrap(), player);
+                let score = match self.cache.get(&key) {
+                    Some(s) => *s,
+                    None => self.estimates.get(&key).map_or(0, |e| e.0),
+                };
+                (score, m)
+            })
+            .collect();
+        match player {
+            Player::PlayerX => moves.sort_by_key(|s| -s.0),
+            Player::PlayerO => moves.sort_by_key(|s| s.0),
+        }
+        moves.into_iter().map(|s| s.1).collect()
+    }
+}
+
+/// Return true if `score` is better than `other` for `player`
+fn is_better_for(player: &Player, score: i8, other: i8) -> bool {
+    match player {
+        Player::PlayerX => score > other,
+        Player::PlayerO => score < other,
+    }
+}
+
+/// Return the best line of play after `player` moved to reach `board`
+fn principal_variation(board: &Board, player: &Player, cache: &mut HashMap<u64, i8>) -> Vec<Move> {
+    let mut pv = Vec::new();
+    let mut board = *board;
+    let mut player = *player;
+    while minimax_score_win_or_draw(&board).is_none() {
+        let opponent = game::switch_player(&player);
+        let mut best: Option<(i8, Move)> = None;
+        for m in find_all_legal_moves(&board) {
+            let new_board = board::make_move(&board, m, &opponent).unwrap();
+            let score = minimax_score(&new_board, &opponent, cache);
+            if best.is_none_or(|b| is_better_for(&opponent, score, b.0)) {
+                best = Some((score, m));
             }
-            Ok(board)
         }
+        let m = best.unwrap().1;
+        board = board::make_move(&board, m, &opponent).unwrap();
+        player = opponent;
+        pv.push(m);
     }
+    pv
+}
+
+/// Return the minimax score of a position, whoever has to play
+pub fn minimax_value(board: &Board, cache: &mut HashMap<u64, i8>) -> i8 {
+    let last_player = game::switch_player(&board::player_to_move(board));
+    minimax_score(board, &last_player, cache)
 }
 
 /// Return for a board, and a player (Player X or O), the best possible score using all legal move
@@ -127,12 +482,14 @@ fn minimax_score(board: &Board, player: &Player, cache: &mut HashMap<u64, i8>) -
 }
 
 /// Return score of a Board if it's a victory or a draw.
+/// A win scores 10 plus the number of empty cases left, so a faster win scores higher.
 fn minimax_score_win_or_draw(board: &Board) -> Option<i8> {
     if let Some(player) = board::is_move_win(board) {
+        let empty_cases = find_all_legal_moves(board).len() as i8;
         if player == Player::PlayerX {
-            return Some(10);
+            return Some(WIN_SCORE + empty_cases);
         } else {
-            return Some(-10);
+            return Some(-WIN_SCORE - empty_cases);
         }
     } else if board::is_board_full(board) {
         return Some(0);
@@ -140,56 +497,39 @@ fn minimax_score_win_or_draw(board: &Board) -> Option<i8> {
     None
 }
 
-fn find_a_blocking_move(
-    legal_moves: &Vec<(usize, usize)>,
+pub(crate) fn find_a_blocking_move(
+    legal_moves: &[Move],
     board: &Board,
     active_player: &Player,
-) -> Option<Board> {
-    let other_player = match active_player {
-        Player::PlayerX => Player::PlayerO,
-        &Player::PlayerO => Player::PlayerX,
-    };
-    let winning_move = find_a_winning_move(legal_moves, board, &other_player);
-    // if winning_move.is_none() {
-    //     return None;
-    // }
-    winning_move?;
-    let mut new_board = winning_move.unwrap();
-    let diff = board::get_difference_between_board_and_next_board(board, &new_board).unwrap();
-    new_board[diff.0 .1][diff.0 .0] = match active_player {
-        Player::PlayerO => Some('O'),
-        Player::PlayerX => Some('X'),
-    };
-    Some(new_board)
+) -> Option<Move> {
+    let other_player = game::switch_player(active_player);
+    find_a_winning_move(legal_moves, board, &other_player)
 }
 
-fn find_a_winning_move(
-    legal_moves: &Vec<(usize, usize)>,
+pub(crate) fn find_a_winning_move(
+    legal_moves: &[Move],
     board: &Board,
     player: &Player,
-) -> Option<Board> {
+) -> Option<Move> {
     for m in legal_moves {
         let new_board = board::make_move(board, *m, player).unwrap();
         if board::is_move_win(&new_board).is_some() {
-            return Some(new_board);
+            return Some(*m);
         }
     }
     None
 }
 
-fn select_one_random_move(
-    legal_moves: &Vec<(usize, usize)>,
-    board: &Board,
-    player: &Player,
-) -> Option<Board> {
+fn select_one_random_move(legal_moves: &[Move]) -> Option<Move> {
+    if legal_moves.is_empty() {
+        return None;
+    }
     let mut rng = rand::thread_rng();
-    let chosen_move = legal_moves[rng.gen_range(0..legal_moves.len())];
-    let new_board = board::make_move(board, chosen_move, player).unwrap();
-    Some(new_board)
+    Some(legal_moves[rng.gen_range(0..legal_moves.len())])
 }
 
 /// return Vec of the possible (y,x) moves
-fn find_all_legal_moves(board: &Board) -> Vec<(usize, usize)> {
+pub fn find_all_legal_moves(board: &Board) -> Vec<Move> {
     let mut legal_moves = Vec::new();
     for (y, line) in board.iter().enumerate() {
         for (x, c) in line.iter().enumerate() {
@@ -218,8 +558,8 @@ mod tests {
             [None, Some('X'), Some('X')],
         ];
         for _ in 0..33 {
-            let new_board = random_ai(&board, &Player::PlayerO).unwrap();
-            assert_ne!(new_board, board);
+            let m = random_ai(&board).unwrap();
+            assert!(board::is_valid_move(&board, m.coord));
         }
     }
 
@@ -244,9 +584,10 @@ mod tests {
             [None, Some('O'), None],
             [None, Some('X'), None],
         ];
-        let legal_moves = vec![(0, 2), (1, 0), (1, 2), (2, 0), (2, 2)];
-        let _new_board = select_one_random_move(&legal_moves, &board, &Player::PlayerX);
-        // TODO : how to assert??
+        let legal_moves = find_all_legal_moves(&board);
+        let m = select_one_random_move(&legal_moves).unwrap();
+        assert!(legal_moves.contains(&m));
+        assert!(select_one_random_move(&[]).is_none());
     }
 
     #[test]
@@ -257,16 +598,11 @@ mod tests {
             [None, None, Some('O')],
             [None, Some('X'), None],
         ];
-        let expected_winning_board = [
-            [Some('O'), Some('X'), None],
-            [None, Some('X'), Some('O')],
-            [None, Some('X'), None],
-        ];
         let legal_moves = vec![(0, 2), (1, 0), (1, 1), (2, 0), (2, 2)];
         for _ in 1..10 {
-            let winning_board = find_a_winning_move(&legal_moves, &board, &Player::PlayerX)
+            let winning_move = find_a_winning_move(&legal_moves, &board, &Player::PlayerX)
                 .expect("unexpected ...");
-            assert_eq!(expected_winning_board, winning_board);
+            assert_eq!((1, 1), winning_move);
         }
     }
 
@@ -278,13 +614,8 @@ mod tests {
             [None, None, Some('O')],
             [None, Some('X'), None],
         ];
-        let expected_winning_board = [
-            [Some('O'), Some('X'), None],
-            [None, Some('X'), Some('O')],
-            [None, Some('X'), None],
-        ];
-        let winning_board = finds_winning_moves_ai(&board, &Player::PlayerX).unwrap();
-        assert_eq!(expected_winning_board, winning_board);
+        let winning_move = finds_winning_moves_ai(&board, &Player::PlayerX).unwrap();
+        assert_eq!((1, 1), winning_move.coord);
     }
 
     #[test]
@@ -295,14 +626,9 @@ mod tests {
             [None, Some('X'), Some('O')],
             [None, None, None],
         ];
-        let expected_board = [
-            [Some('O'), Some('X'), None],
-            [None, Some('X'), Some('O')],
-            [None, Some('O'), None],
-        ];
         let legal_moves = vec![(0, 2), (1, 0), (2, 0), (2, 1), (2, 2)];
-        let new_board = find_a_blocking_move(&legal_moves, &board, &Player::PlayerO).unwrap();
-        assert_eq!(expected_board, new_board);
+        let blocking_move = find_a_blocking_move(&legal_moves, &board, &Player::PlayerO).unwrap();
+        assert_eq!((2, 1), blocking_move);
     }
 
     #[test]
@@ -313,14 +639,8 @@ mod tests {
             [None, Some('X'), Some('O')],
             [None, None, None],
         ];
-        let expected_board = [
-            [Some('O'), Some('X'), None],
-            [None, Some('X'), Some('O')],
-            [None, Some('O'), None],
-        ];
-        //let legal_moves = vec![(0, 2), (1, 0), (2, 0), (2, 1), (2, 2)];
-        let new_board = finds_winning_and_not_losing_moves_ai(&board, &Player::PlayerO).unwrap();
-        assert_eq!(expected_board, new_board);
+        let new_move = finds_winning_and_not_losing_moves_ai(&board, &Player::PlayerO).unwrap();
+        assert_eq!((2, 1), new_move.coord);
 
         // TODO : more variants
     }
@@ -355,7 +675,7 @@ mod tests {
             [None, Some('X'), Some('X')],
         ];
         let s = minimax_score(&loosing_board, &active_player, &mut cache);
-        assert_eq!(10, s);
+        assert_eq!(12, s);
         cache.clear();
 
         let draw_board = [
@@ -367,4 +687,210 @@ mod tests {
         assert_eq!(0, s);
         cache.clear();
     }
+
+    #[test]
+    fn test_minimax_algo_ai() {
+        init();
+        let board = [
+            [Some('X'), None, Some('O')],
+            [None, Some('X'), None],
+            [Some('O'), None, None],
+        ];
+        let mut cache: HashMap<u64, i8> = HashMap::new();
+        let m = minimax_algo_ai(
+            &board,
+            &Player::PlayerX,
+            &mut cache,
+            &SearchLimits::default(),
+            TieBreak::First,
+        )
+        .unwrap();
+        // (0, 1) also wins, but later
+        assert_eq!((2, 2), m.coord);
+        assert_eq!(Some(14), m.score);
+        assert_eq!(vec![(2, 2)], m.pv);
+
+        let m = minimax_algo_ai(
+            &board,
+            &Player::PlayerO,
+            &mut cache,
+            &SearchLimits::default(),
+            TieBreak::First,
+        )
+        .unwrap();
+        assert_eq!((2, 2), m.coord);
+        assert_eq!(Some(-12), m.score);
+        assert_eq!(3, m.pv.len());
+
+        let limits = SearchLimits::from_budget(Some(Duration::ZERO));
+        let m = minimax_algo_ai(
+            &board,
+            &Player::PlayerO,
+            &mut HashMap::new(),
+            &limits,
+            TieBreak::First,
+        )
+        .unwrap();
+        assert!(board::is_valid_move(&board, m.coord));
+    }
+
+    #[test]
+    fn test_minimax_algo_ai_node_budget() {
+        init();
+        let board = [
+            [Some('X'), None, Some('O')],
+            [None, Some('X'), None],
+            [Some('O'), None, None],
+        ];
+        // a small budget is enough to see the threat on (2, 2) but not to solve the position
+        let limits = SearchLimits {
+            deadline: None,
+            nodes: Some(6),
+        };
+        let mut cache: HashMap<u64, i8> = HashMap::new();
+        let m = minimax_algo_ai(
+            &board,
+            &Player::PlayerO,
+            &mut cache,
+            &limits,
+            TieBreak::First,
+        )
+        .unwrap();
+        assert_eq!((2, 2), m.coord);
+        assert_eq!(vec![(2, 2)], m.pv);
+
+        let m = minimax_algo_ai(
+            &board,
+            &Player::PlayerO,
+            &mut cache,
+            &SearchLimits::default(),
+            TieBreak::First,
+        )
+        .unwrap();
+        assert_eq!((2, 2), m.coord);
+        assert_eq!(Some(-12), m.score);
+    }
+
+    #[test]
+    fn test_pick_move_with_blunders() {
+        init();
+        let scores = vec![(0, (0, 0)), (10, (0, 1)), (-10, (0, 2))];
+        for _ in 0..20 {
+            assert_eq!(
+                (0, 1),
+                pick_move_with_blunders(&scores, &Player::PlayerX, 1.0).1
+            );
+            assert_ne!(
+                (0, 1),
+                pick_move_with_blunders(&scores, &Player::PlayerX, 0.0).1
+            );
+            assert_eq!(
+                (0, 2),
+                pick_move_with_blunders(&scores, &Player::PlayerO, 1.0).1
+            );
+        }
+        let draws = vec![(0, (0, 0)), (0, (0, 1))];
+        assert_eq!(0, pick_move_with_blunders(&draws, &Player::PlayerX, 0.0).0);
+    }
+
+    #[test]
+    fn test_level_ai() {
+        init();
+        let board = [
+            [Some('X'), None, Some('O')],
+            [None, Some('X'), None],
+            [Some('O'), None, None],
+        ];
+        let mut cache: HashMap<u64, i8> = HashMap::new();
+        for _ in 0..10 {
+            let limits = SearchLimits::default();
+            let m = level_ai(&board, &Player::PlayerO, 10, &mut cache, &limits).unwrap();
+            assert_eq!((2, 2), m.coord);
+            let m = level_ai(&board, &Player::PlayerO, 1, &mut cache, &limits).unwrap();
+            assert!(board::is_valid_move(&board, m.coord));
+        }
+    }
+
+    #[test]
+    fn test_level_ai_limits() {
+        init();
+        let board = [
+            [Some('X'), Some('X'), None],
+            [Some('O'), Some('O'), None],
+            [None, None, None],
+        ];
+        // only the first iteration completes, and it sees the immediate win
+        let limits = SearchLimits {
+            deadline: None,
+            nodes: Some(0),
+        };
+        let mut cache: HashMap<u64, i8> = HashMap::new();
+        let m = level_ai(&board, &Player::PlayerX, 10, &mut cache, &limits).unwrap();
+        assert_eq!((0, 2), m.coord);
+        assert!(cache.len() < 10);
+    }
+
+    #[test]
+    fn test_break_tie() {
+        init();
+        let scores = vec![(0, (0, 1)), (0, (0, 0)), (0, (1, 1)), (-3, (2, 2))];
+        assert_eq!(
+            (0, 0),
+            break_tie(&scores, &Player::PlayerX, TieBreak::First).1
+        );
+        assert_eq!(
+            (1, 1),
+            break_tie(&scores, &Player::PlayerX, TieBreak::Preferred).1
+        );
+        assert_eq!(
+            (2, 2),
+            break_tie(&scores, &Player::PlayerO, TieBreak::Preferred).1
+        );
+        for _ in 0..10 {
+            assert_eq!(0, break_tie(&scores, &Player::PlayerX, TieBreak::Random).0);
+        }
+    }
+
+    #[test]
+    fn test_outcome() {
+        init();
+        let board = [
+            [Some('X'), None, Some('O')],
+            [None, Some('X'), None],
+            [Some('O'), None, None],
+        ];
+        let mut cache: HashMap<u64, i8> = HashMap::new();
+        let scores = minimax_scores(&board, &Player::PlayerO, &mut cache);
+        for (score, m) in scores {
+            let o = outcome(score, &Player::PlayerO, 5);
+            if m == (2, 2) {
+                assert_eq!(Outcome::Win(3), o);
+            } else {
+                assert_eq!(Outcome::Loss(2), o);
+            }
+        }
+        assert_eq!(Outcome::Draw, outcome(0, &Player::PlayerX, 5));
+        assert_eq!("win in 3 moves", Outcome::Win(3).to_string());
+        assert_eq!("win in 1 move", Outcome::Win(1).to_string());
+        assert_eq!("loss in 2 moves", Outcome::Loss(2).to_string());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_json_shape() {
+        use serde_json::json;
+        init();
+        let engine_move = EngineMove {
+            coord: (0, 2),
+            score: Some(14),
+            pv: vec![(0, 2)],
+        };
+        let value = serde_json::to_value(&engine_move).unwrap();
+        assert_eq!(json!({"coord": [0, 2], "score": 14, "pv": [[0, 2]]}), value);
+        assert_eq!(engine_move, serde_json::from_value(value).unwrap());
+        let outside = json!({"coord": [0, 3], "score": null, "pv": []});
+        assert!(serde_json::from_value::<EngineMove>(outside).is_err());
+        let outside = json!({"coord": [0, 2], "score": null, "pv": [[0, 2], [5, 5]]});
+        assert!(serde_json::from_value::<EngineMove>(outside).is_err());
+    }
 }
@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use log::error;
+
+use crate::ai::{self, Outcome};
+use crate::board::{self, Move};
+use crate::game::{self, GameRecord};
+use crate::observer::{EndReason, GameObserver};
+use crate::player::Player;
+
+/// Quality of a move compared to perfect play
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[non_exhaustive]
+pub enum Annotation {
+    /// the best outcome is still reachable
+    Best,
+    /// a won game becomes a draw
+    Inaccuracy,
+    /// a game that was not lost becomes lost
+    Blunder,
+}
+
+/// A move of a game, annotated
+#[derive(Debug, PartialEq, Clone)]
+pub struct MoveAnalysis {
+    /// player of the move
+    pub player: Player,
+    /// case played
+    pub coord: Move,
+    /// outcome of the move played
+    pub played: Outcome,
+    /// outcome of the best move
+    pub best: Outcome,
+    /// quality of the move played
+    pub annotation: Annotation,
+}
+
+/// Run every position of a game through the minimax solver and annotate each move
+pub fn analyze_game(record: &GameRecord) -> Vec<MoveAnalysis> {
+    let mut cache: HashMap<u64, i8> = HashMap::new();
+    let mut board = board::generate_new_board();
+    let mut player = Player::PlayerX;
+    let mut analysis = Vec::new();
+
+    for coord in &record.moves {
+        let empty_cases = ai::find_all_legal_moves(&board).len();
+        let outcomes: Vec<(Outcome, Move)> = ai::minimax_scores(&board, &player, &mut cache)
+            .into_iter()
+            .map(|(score, m)| (ai::outcome(score, &player, empty_cases), m))
+            .collect();
+        let played = outcomes.iter().find(|o| o.1 == *coord).unwrap().0;
+        let best = outcomes.iter().map(|o| o.0).max_by_key(rank).unwrap();
+        let annotation = match (rank(&best), rank(&played)) {
+            (b, p) if b == p => Annotation::Best,
+            (2, 1) => Annotation::Inaccuracy,
+            _ => Annotation::Blunder,
+        };
+        analysis.push(MoveAnalysis {
+            player,
+            coord: *coord,
+            played,
+            best,
+            annotation,
+        });
+
+        board = board::make_move(&board, *coord, &player).unwrap();
+        player = game::switch_player(&player);
+    }
+    analysis
+}
+
+/// 2 for a win, 1 for a draw, 0 for a loss
+fn rank(outcome: &Outcome) -> u8 {
+    match outcome {
+        Outcome::Win(_) => 2,
+        Outcome::Draw => 1,
+        Outcome::Loss(_) => 0,
+    }
+}
+
+/// Print the annotated moves of a game and a summary per player
+pub fn print_analysis(out: &mut impl Write, analysis: &[MoveAnalysis]) -> io::Result<()> {
+    writeln!(out, "Analysis :")?;
+    for (i, a) in analysis.iter().enumerate() {
+        match a.annotation {
+            Annotation::Best => writeln!(
+                out,
+                "{}. {:?} {} : best ({})",
+                i + 1,
+                a.player,
+                board::format_move(a.coord),
+                a.played
+            )?,
+            _ => writeln!(
+                out,
+                "{}. {:?} {} : {:?} ({} instead of {})",
+                i + 1,
+                a.player,
+                board::format_move(a.coord),
+                a.annotation,
+                a.played,
+                a.best
+            )?,
+        }
+    }
+    for player in [Player::PlayerX, Player::PlayerO] {
+        let count = |annotation: Annotation| {
+            analysis
+                .iter()
+                .filter(|a| a.player == player && a.annotation == annotation)
+                .count()
+        };
+        writeln!(
+            out,
+            "{:?} : {} best, {} inaccuracies, {} blunders",
+            player,
+            count(Annotation::Best),
+            count(Annotation::Inaccuracy),
+            count(Annotation::Blunder)
+        )?;
+    }
+    Ok(())
+}
+
+/// Print the analysis of every game to `out` when it ends
+#[derive(Debug)]
+pub struct AnalysisObserver<W: Write> {
+    /// where the analysis is printed
+    pub out: W,
+}
+
+impl<W: Write> GameObserver for AnalysisObserver<W> {
+    fn on_game_ended(&mut self, record: &GameRecord, _reason: EndReason) {
+        if let Err(e) = print_analysis(&mut self.out, &analyze_game(record)) {
+            error!("Error printing the analysis : {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn init() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[test]
+    fn test_analyze_game() {
+        init();
+        // X : center, O : side (loses), X : corner (wins), O : blocks, X : draws
+        let record = GameRecord {
+            moves: vec![(1, 1), (0, 1), (0, 0), (2, 2), (0, 2)],
+            winner: None,
+        };
+        let analysis = analyze_game(&record);
+        assert_eq!(5, analysis.len());
+        assert_eq!(Annotation::Best, analysis[0].annotation);
+        assert_eq!(Outcome::Draw, analysis[0].played);
+        assert_eq!(Annotation::Blunder, analysis[1].annotation);
+        assert_eq!(Player::PlayerO, analysis[1].player);
+        assert_eq!(Annotation::Best, analysis[2].annotation);
+        assert_eq!(Annotation::Best, analysis[3].annotation);
+        assert_eq!(Annotation::Inaccuracy, analysis[4].annotation);
+    }
+}
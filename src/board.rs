@@ -1,26 +1,26 @@
 use crate::player::Player;
 use anyhow::{bail, Result};
 
+/// Rows of cases, each one 'X', 'O' or None. Serialized by serde as three arrays of three
+/// cases, like `[["X", null, "O"], [null, "X", null], ["O", null, null]]`, validated by
+/// [`serde_board`].
 pub type Board = [[Option<char>; 3]; 3];
-pub type DiffBoard = ((usize, usize), Option<char>);
+/// (y,x) coordinates of a case : `board[y][x]`. Serialized by serde as `[y, x]`, validated by
+/// [`serde_move`] and [`serde_moves`].
+pub type Move = (usize, usize);
 
-/// Get the difference between a board and a board generate by a new move (so there is only one difference)
-pub fn get_difference_between_board_and_next_board(
-    board: &Board,
-    next_board: &Board,
-) -> Option<DiffBoard> {
-    for (y, line) in board.iter().enumerate() {
-        for (x, c) in line.iter().enumerate() {
-            if c.unwrap_or_default()
-                .ne(&next_board[y][x].unwrap_or_default())
-            {
-                return Some(((x, y), *c));
-            }
-        }
-    }
-    None
-}
+const THREE_CASES_LINES: [[Move; 3]; 8] = [
+    [(0, 0), (0, 1), (0, 2)],
+    [(1, 0), (1, 1), (1, 2)],
+    [(2, 0), (2, 1), (2, 2)],
+    [(0, 0), (1, 0), (2, 0)],
+    [(0, 1), (1, 1), (2, 1)],
+    [(0, 2), (1, 2), (2, 2)],
+    [(0, 0), (1, 1), (2, 2)],
+    [(0, 2), (1, 1), (2, 0)],
+];
 
+/// Empty board
 pub fn generate_new_board() -> Board {
     //debug!("Generate a new and clean board");
     [[None, None, None], [None, None, None], [None, None, None]]
@@ -36,37 +36,55 @@ fn duplicate_board(board: &Board) -> Board {
     new_board
 }
 
-pub fn is_valid_move(board: &Board, new_move: (usize, usize)) -> bool {
-    board[new_move.0][new_move.1].is_none()
+/// Whether the move is on the board and its case empty
+pub fn is_valid_move(board: &Board, new_move: Move) -> bool {
+    board
+        .get(new_move.0)
+        .and_then(|line| line.get(new_move.1))
+        .is_some_and(|case| case.is_none())
 }
 
+/// Player with a line on the board, if any
 pub fn is_move_win(board: &Board) -> Option<Player> {
-    let three_cases_lines = [
-        [(0, 0), (0, 1), (0, 2)],
-        [(1, 0), (1, 1), (1, 2)],
-        [(2, 0), (2, 1), (2, 2)],
-        [(0, 0), (1, 0), (2, 0)],
-        [(0, 1), (1, 1), (2, 1)],
-        [(0, 2), (1, 2), (2, 2)],
-        [(0, 0), (1, 1), (2, 2)],
-        [(0, 2), (1, 1), (2, 0)],
-    ];
-
-    for line in three_cases_lines {
-        if board[line[0].0][line[0].1] == board[line[1].0][line[1].1]
+    let line = winning_line(board)?;
+    if board[line[0].0][line[0].1] == Some('X') {
+        Some(Player::PlayerX)
+    } else {
+        Some(Player::PlayerO)
+    }
+}
+
+/// Return the three cases of the line completed by the winner, if any
+pub fn winning_line(board: &Board) -> Option<[Move; 3]> {
+    THREE_CASES_LINES.into_iter().find(|line| {
+        board[line[0].0][line[0].1] == board[line[1].0][line[1].1]
             && board[line[1].0][line[1].1] == board[line[2].0][line[2].1]
             && (board[line[0].0][line[0].1].is_some())
-        {
-            if board[line[0].0][line[0].1] == Some('X') {
-                return Some(Player::PlayerX);
-            } else {
-                return Some(Player::PlayerO);
-            }
-        }
+    })
+}
+
+/// Check that the position can be reached in a game : X plays first, the players take turns
+/// and the game stops at the first line
+pub fn check_reachable(board: &Board) -> Result<()> {
+    let count = |p: char| board.iter().flatten().filter(|c| **c == Some(p)).count();
+    let (x, o) = (count('X'), count('O'));
+    if x != o && x != o + 1 {
+        bail!("{} X and {} O can not be reached, X playing first", x, o);
+    }
+    let has_line = |p: char| {
+        THREE_CASES_LINES
+            .iter()
+            .any(|line| line.iter().all(|m| board[m.0][m.1] == Some(p)))
+    };
+    match (has_line('X'), has_line('O')) {
+        (true, true) => bail!("both players have a line"),
+        (true, false) if x == o => bail!("O played after the line of X"),
+        (false, true) if x > o => bail!("X played after the line of O"),
+        _ => Ok(()),
     }
-    None
 }
 
+/// Board as plain characters, with its coordinates
 pub fn render_board(board: &Board) -> Result<String> {
     let mut output = "  0 1 2\n".to_owned();
     output += " -------\n";
@@ -85,7 +103,8 @@ pub fn render_board(board: &Board) -> Result<String> {
     Ok(output)
 }
 
-pub fn make_move(board: &Board, new_move: (usize, usize), player: &Player) -> Result<Board> {
+/// Board after the move of `player`, an error if the move is not valid
+pub fn make_move(board: &Board, new_move: Move, player: &Player) -> Result<Board> {
     if !is_valid_move(board, new_move) {
         bail!("Invalid move !");
     }
@@ -97,6 +116,96 @@ pub fn make_move(board: &Board, new_move: (usize, usize), player: &Player) -> Re
     Ok(new_board)
 }
 
+/// Parse a board written line by line, "X", "O" or "." for each case, lines being
+/// optionally separated by "/" : "X.O/.X./O.."
+pub fn parse_board(s: &str) -> Result<Board> {
+    let cases: Vec<char> = s.chars().filter(|c| *c != '/').collect();
+    if cases.len() != 9 {
+        bail!("A board needs 9 cases, {} given", cases.len());
+    }
+    let mut board = generate_new_board();
+    for (i, c) in cases.iter().enumerate() {
+        board[i / 3][i % 3] = match c.to_ascii_uppercase() {
+            'X' => Some('X'),
+            'O' => Some('O'),
+            '.' => None,
+            _ => bail!("Unexpected case {} in board", c),
+        };
+    }
+    Ok(board)
+}
+
+/// Write a board on one line, the format read by `parse_board`
+pub fn board_to_string(board: &Board) -> String {
+    board
+        .iter()
+        .map(|line| line.iter().map(|c| c.unwrap_or('.')).collect::<String>())
+        .collect::<Vec<String>>()
+        .join("/")
+}
+
+/// Format a (y,x) move as typed by a player : "x,y"
+pub fn format_move(m: Move) -> String {
+    format!("{},{}", m.1, m.0)
+}
+
+/// Player whose turn it is, X playing first
+pub fn player_to_move(board: &Board) -> Player {
+    let count = |p: char| board.iter().flatten().filter(|c| **c == Some(p)).count();
+    if count('X') > count('O') {
+        Player::PlayerO
+    } else {
+        Player::PlayerX
+    }
+}
+
+/// Apply one of the 8 symmetries of the board (0 to 3 : rotations by a quarter turn, 4 to 7 :
+/// the same rotations after a reflection) to a move
+pub fn transform_move(m: Move, symmetry: usize) -> Move {
+    let (mut y, mut x) = m;
+    if symmetry >= 4 {
+        x = 2 - x;
+    }
+    for _ in 0..symmetry % 4 {
+        (y, x) = (x, 2 - y);
+    }
+    (y, x)
+}
+
+/// Return the symmetry undoing `symmetry`
+pub fn inverse_symmetry(symmetry: usize) -> usize {
+    (0..8)
+        .find(|s| {
+            [(0, 0), (0, 1)]
+                .iter()
+                .all(|m| transform_move(transform_move(*m, symmetry), *s) == *m)
+        })
+        .unwrap()
+}
+
+/// Board transformed by the symmetry `symmetry` (from 0 to 7) of the square, as
+/// `transform_move`
+pub fn transform_board(board: &Board, symmetry: usize) -> Board {
+    let mut new_board = generate_new_board();
+    for (y, line) in board.iter().enumerate() {
+        for (x, c) in line.iter().enumerate() {
+            let (ny, nx) = transform_move((y, x), symmetry);
+            new_board[ny][nx] = *c;
+        }
+    }
+    new_board
+}
+
+/// Return the representative of the board among its 8 symmetries, and the symmetry
+/// transforming the board into it
+pub fn canonical_board(board: &Board) -> (Board, usize) {
+    (0..8)
+        .map(|s| (transform_board(board, s), s))
+        .min()
+        .unwrap()
+}
+
+/// Whether every case is taken
 pub fn is_board_full(board: &Board) -> bool {
     for line in board {
         for case in line {
@@ -108,6 +217,87 @@ pub fn is_board_full(board: &Board) -> bool {
     true
 }
 
+/// Serde helpers of a `Board` field, `#[serde(with = "tictactoe::board::serde_board")]`,
+/// rejecting the cases other than "X", "O" and null
+#[cfg(feature = "serde")]
+pub mod serde_board {
+    use super::Board;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    /// Serialize the board as three rows of cases
+    pub fn serialize<S: Serializer>(board: &Board, serializer: S) -> Result<S::Ok, S::Error> {
+        board.serialize(serializer)
+    }
+
+    /// Deserialize three rows of "X", "O" or null
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Board, D::Error> {
+        let board = Board::deserialize(deserializer)?;
+        match board
+            .iter()
+            .flatten()
+            .flatten()
+            .find(|c| **c != 'X' && **c != 'O')
+        {
+            Some(c) => Err(D::Error::custom(format!(
+                "invalid case {:?}, expected \"X\", \"O\" or null",
+                c
+            ))),
+            None => Ok(board),
+        }
+    }
+}
+
+/// Serde helpers of a `Move` field, `#[serde(with = "tictactoe::board::serde_move")]`,
+/// rejecting the coordinates out of the board
+#[cfg(feature = "serde")]
+pub mod serde_move {
+    use super::Move;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    /// Serialize the move as `[y, x]`
+    pub fn serialize<S: Serializer>(m: &Move, serializer: S) -> Result<S::Ok, S::Error> {
+        m.serialize(serializer)
+    }
+
+    /// Deserialize `[y, x]`, both from 0 to 2
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Move, D::Error> {
+        let m = Move::deserialize(deserializer)?;
+        check(m).map_err(D::Error::custom)
+    }
+
+    pub(super) fn check(m: Move) -> Result<Move, String> {
+        if m.0 <= 2 && m.1 <= 2 {
+            Ok(m)
+        } else {
+            Err(format!("invalid move {:?}, expected [0 to 2, 0 to 2]", m))
+        }
+    }
+}
+
+/// Serde helpers of a `Vec<Move>` field, `#[serde(with = "tictactoe::board::serde_moves")]`,
+/// rejecting the coordinates out of the board
+#[cfg(feature = "serde")]
+pub mod serde_moves {
+    use super::Move;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    /// Serialize the moves as `[[y, x], ...]`
+    pub fn serialize<S: Serializer>(moves: &[Move], serializer: S) -> Result<S::Ok, S::Error> {
+        moves.serialize(serializer)
+    }
+
+    /// Deserialize `[[y, x], ...]`, every coordinate from 0 to 2
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Move>, D::Error> {
+        Vec::<Move>::deserialize(deserializer)?
+            .into_iter()
+            .map(|m| super::serde_move::check(m).map_err(D::Error::custom))
+            .collect()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -178,6 +368,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn test_is_valid_move() {
         init();
         let board: [[Option<char>; 3]; 3] = [
@@ -189,9 +380,13 @@ mod tests {
         assert_eq!(true, is_valid_move(&board, mv));
         let mv: (usize, usize) = (0, 0);
         assert_eq!(false, is_valid_move(&board, mv));
+        assert_eq!(false, is_valid_move(&board, (3, 0)));
+        assert_eq!(false, is_valid_move(&board, (0, usize::MAX)));
+        assert!(make_move(&board, (1, 3), &Player::PlayerX).is_err());
     }
 
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn test_is_board_full() {
         init();
         let board_full: [[Option<char>; 3]; 3] = [
@@ -233,5 +428,122 @@ mod tests {
         assert_eq!(p1, Player::PlayerX);
         assert_eq!(p2, Player::PlayerO);
         assert!(p3.is_none());
+        assert_eq!(
+            Some([(0, 2), (1, 1), (2, 0)]),
+            winning_line(&another_board_win)
+        );
+        assert_eq!(None, winning_line(&board_not_win));
+    }
+
+    #[test]
+    fn test_player_to_move() {
+        init();
+        assert_eq!(Player::PlayerX, player_to_move(&generate_new_board()));
+        let board: Board = [[Some('X'), None, None], [None; 3], [None; 3]];
+        assert_eq!(Player::PlayerO, player_to_move(&board));
+    }
+
+    #[test]
+    fn test_symmetries() {
+        init();
+        let board: Board = [
+            [Some('X'), Some('O'), None],
+            [None, None, None],
+            [None, None, None],
+        ];
+        let mut boards: Vec<Board> = (0..8).map(|s| transform_board(&board, s)).collect();
+        boards.sort();
+        boards.dedup();
+        assert_eq!(8, boards.len());
+
+        for s in 0..8 {
+            let transformed = transform_board(&board, s);
+            assert_eq!(board, transform_board(&transformed, inverse_symmetry(s)));
+            assert_eq!(canonical_board(&board).0, canonical_board(&transformed).0);
+        }
+        let (canonical, s) = canonical_board(&board);
+        assert_eq!(canonical, transform_board(&board, s));
+    }
+
+    #[test]
+    fn test_parse_board() {
+        init();
+        let board: Board = [
+            [Some('X'), None, Some('O')],
+            [None, Some('X'), None],
+            [Some('O'), None, None],
+        ];
+        assert_eq!(board, parse_board("X.O/.X./O..").unwrap());
+        assert_eq!(board, parse_board("x.o.x.o..").unwrap());
+        assert_eq!("X.O/.X./O..", board_to_string(&board));
+        assert!(parse_board("X.O/.X.").is_err());
+        assert!(parse_board("X.O/.X./O.A").is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_json_shape() {
+        use serde_json::json;
+        init();
+        let board = parse_board("X.O/.X./...").unwrap();
+        let value = serde_board::serialize(&board, serde_json::value::Serializer).unwrap();
+        assert_eq!(
+            json!([["X", null, "O"], [null, "X", null], [null, null, null]]),
+            value
+        );
+        assert_eq!(board, serde_board::deserialize(value).unwrap());
+        for invalid in [
+            json!([["Z", null, null], [null, null, null], [null, null, null]]),
+            json!([["x", null, null], [null, null, null], [null, null, null]]),
+            json!([["X", null, null], [null, null, null]]),
+        ] {
+            assert!(serde_board::deserialize(invalid).is_err());
+        }
+
+        let value = serde_move::serialize(&(0, 2), serde_json::value::Serializer).unwrap();
+        assert_eq!(json!([0, 2]), value);
+        assert_eq!((0, 2), serde_move::deserialize(value).unwrap());
+        assert!(serde_move::deserialize(json!([0, 3])).is_err());
+        assert!(serde_move::deserialize(json!([-1, 0])).is_err());
+        assert_eq!(
+            vec![(1, 1), (2, 0)],
+            serde_moves::deserialize(json!([[1, 1], [2, 0]])).unwrap()
+        );
+        assert!(serde_moves::deserialize(json!([[1, 1], [3, 0]])).is_err());
+    }
+
+    #[test]
+    fn test_format_move() {
+        init();
+        assert_eq!("1,2", format_move((2, 1)));
+        assert_eq!(
+            (2, 1),
+            crate::player::get_input_from_keyboard(&format_move((2, 1))).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_check_reachable() {
+        init();
+        for reachable in [
+            ".../.../...",
+            "X../.../...",
+            "XXX/OO./...",
+            "XXO/OOX/XXO",
+            "XOX/.O./.OX",
+        ] {
+            assert!(check_reachable(&parse_board(reachable).unwrap()).is_ok());
+        }
+        // X winning with two lines at once
+        assert!(check_reachable(&parse_board("XXX/XOO/XOO").unwrap()).is_ok());
+        for impossible in [
+            "OOO/.../...",
+            "XX./.../...",
+            "XXX/OOO/X..",
+            "XXX/OOO/...",
+            "OOO/XX./XX.",
+        ] {
+            assert!(check_reachable(&parse_board(impossible).unwrap()).is_err());
+        }
     }
 }
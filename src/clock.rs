@@ -0,0 +1,104 @@
+use std::time::Duration;
+
+/// Time allowed to each player : a budget for the whole game (credited with an increment
+/// after each move) and/or a limit for every single move. `None` means no limit.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct TimeControl {
+    /// time for the whole game
+    pub base: Option<Duration>,
+    /// time added after each move
+    pub increment: Duration,
+    /// time for each move
+    pub per_move: Option<Duration>,
+}
+
+/// Clock of one player during a game
+#[derive(Debug, Clone)]
+pub(crate) struct Clock {
+    time_control: TimeControl,
+    remaining: Option<Duration>,
+}
+
+impl Clock {
+    pub fn new(time_control: TimeControl) -> Clock {
+        Clock {
+            time_control,
+            remaining: time_control.base,
+        }
+    }
+
+    /// Time left on the game budget, None if the game is not timed
+    pub fn remaining(&self) -> Option<Duration> {
+        self.remaining
+    }
+
+    /// Time the player can spend on its next move, None if unlimited
+    pub fn move_budget(&self) -> Option<Duration> {
+        match (self.remaining, self.time_control.per_move) {
+            (Some(r), Some(m)) => Some(r.min(m)),
+            (r, m) => r.or(m),
+        }
+    }
+
+    /// Charge the time spent on a move. Return false if the flag fell.
+    pub fn punch(&mut self, elapsed: Duration) -> bool {
+        if let Some(budget) = self.move_budget() {
+            if elapsed >= budget {
+                self.remaining = self.remaining.map(|_| Duration::ZERO);
+                return false;
+            }
+        }
+        self.remaining = self
+            .remaining
+            .map(|r| r - elapsed + self.time_control.increment);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn init() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[test]
+    fn test_move_budget() {
+        init();
+        let clock = Clock::new(TimeControl::default());
+        assert_eq!(None, clock.move_budget());
+
+        let clock = Clock::new(TimeControl {
+            base: Some(Duration::from_secs(10)),
+            increment: Duration::ZERO,
+            per_move: Some(Duration::from_secs(2)),
+        });
+        assert_eq!(Some(Duration::from_secs(2)), clock.move_budget());
+
+        let clock = Clock::new(TimeControl {
+            base: Some(Duration::from_secs(1)),
+            increment: Duration::ZERO,
+            per_move: Some(Duration::from_secs(2)),
+        });
+        assert_eq!(Some(Duration::from_secs(1)), clock.move_budget());
+    }
+
+    #[test]
+    fn test_punch() {
+        init();
+        let mut clock = Clock::new(TimeControl {
+            base: Some(Duration::from_secs(10)),
+            increment: Duration::from_secs(1),
+            per_move: Some(Duration::from_secs(5)),
+        });
+        assert!(clock.punch(Duration::from_secs(3)));
+        assert_eq!(Some(Duration::from_secs(8)), clock.remaining());
+        assert!(!clock.punch(Duration::from_secs(6)));
+        assert_eq!(Some(Duration::ZERO), clock.remaining());
+
+        let mut clock = Clock::new(TimeControl::default());
+        assert!(clock.punch(Duration::from_secs(3600)));
+        assert_eq!(None, clock.remaining());
+    }
+}
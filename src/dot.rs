@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+
+use crate::ai;
+use crate::board::{self, Board};
+
+/// Export the game tree from `board` as a Graphviz DOT graph. Nodes are labelled with the
+/// board and its minimax value, edges with the move. The tree is limited to `depth` moves
+/// if given, and positions equal by symmetry are merged if `reduce_symmetry` is set.
+/// Fail if `board` can't occur in a game.
+pub fn game_tree_to_dot(
+    board: &Board,
+    depth: Option<usize>,
+    reduce_symmetry: bool,
+) -> Result<String> {
+    board::check_reachable(board)?;
+    let key = |b: &Board| {
+        if reduce_symmetry {
+            board::canonical_board(b).0
+        } else {
+            *b
+        }
+    };
+    let mut cache: HashMap<u64, i8> = HashMap::new();
+    let mut ids: HashMap<Board, usize> = HashMap::new();
+    let mut nodes = String::new();
+    let mut edges = String::new();
+
+    let mut to_visit = vec![(*board, 0)];
+    ids.insert(key(board), 0);
+    nodes += &node_to_dot(0, board, &mut cache);
+    while let Some((b, d)) = to_visit.pop() {
+        if board::is_move_win(&b).is_some() || depth.is_some_and(|max| d >= max) {
+            continue;
+        }
+        let parent_id = ids[&key(&b)];
+        let player = board::player_to_move(&b);
+        let mut children: Vec<usize> = Vec::new();
+        for m in ai::find_all_legal_moves(&b) {
+            let child = board::make_move(&b, m, &player).unwrap();
+            let child_id = match ids.get(&key(&child)) {
+                Some(id) => *id,
+                None => {
+                    let id = ids.len();
+                    ids.insert(key(&child), id);
+                    nodes += &node_to_dot(id, &child, &mut cache);
+                    to_visit.push((child, d + 1));
+                    id
+                }
+            };
+            if !children.contains(&child_id) {
+                children.push(child_id);
+                edges += &format!(
+                    "  n{} -> n{} [label=\"{}\"];\n",
+                    parent_id,
+                    child_id,
+                    board::format_move(m)
+                );
+            }
+        }
+    }
+
+    Ok(format!(
+        "digraph game_tree {{\n  node [shape=box, fontname=\"monospace\"];\n{}{}}}\n",
+        nodes, edges
+    ))
+}
+
+fn node_to_dot(id: usize, board: &Board, cache: &mut HashMap<u64, i8>) -> String {
+    let value = ai::minimax_value(board, cache);
+    format!(
+        "  n{} [label=\"{}\\nvalue: {}\"];\n",
+        id,
+        board::board_to_string(board).replace('/', "\\n"),
+        value
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn init() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[test]
+    fn test_game_tree_to_dot() {
+        init();
+        let empty = board::generate_new_board();
+        let dot = game_tree_to_dot(&empty, Some(1), false).unwrap();
+        assert!(dot.starts_with("digraph game_tree {"));
+        assert_eq!(9, dot.matches("->").count());
+        assert_eq!(10, dot.matches("value:").count());
+
+        let dot = game_tree_to_dot(&empty, Some(1), true).unwrap();
+        assert_eq!(3, dot.matches("->").count());
+        assert_eq!(4, dot.matches("value:").count());
+
+        let board = board::parse_board("XO./XO./...").unwrap();
+        let dot = game_tree_to_dot(&board, None, false).unwrap();
+        assert!(dot.contains("n0 [label=\"XO.\\nXO.\\n...\\nvalue: 14\"];"));
+
+        for impossible in ["OO./.../...", "XXX/OOO/...", "XXX/OO./O.."] {
+            let board = board::parse_board(impossible).unwrap();
+            assert!(game_tree_to_dot(&board, Some(1), false).is_err());
+        }
+    }
+}
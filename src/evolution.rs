@@ -0,0 +1,227 @@
+use anyhow::{bail, Result};
+use rand::Rng;
+
+use crate::clock::TimeControl;
+use crate::game::{self, Engine, EngineData};
+use crate::heuristic::{HeuristicWeights, FEATURES};
+
+/// Parameters of the genetic algorithm
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct EvolutionParams {
+    /// number of weight vectors in each generation, at least 2
+    pub population: usize,
+    /// number of generations
+    pub generations: usize,
+    /// standard deviation of the noise added to each weight of a child
+    pub mutation: f64,
+    /// number of best individuals kept unchanged in the next generation
+    pub elitism: usize,
+}
+
+impl Default for EvolutionParams {
+    fn default() -> Self {
+        EvolutionParams {
+            population: 20,
+            generations: 20,
+            mutation: 1.0,
+            elitism: 2,
+        }
+    }
+}
+
+/// Evolve weight vectors maximizing `fitness`, starting from mutations of `initial`.
+/// `on_generation` is called with the generation number and its best individual.
+/// Return the best weights of the last generation, or the first error of `fitness`.
+pub fn evolve(
+    initial: &HeuristicWeights,
+    params: &EvolutionParams,
+    mut fitness: impl FnMut(&HeuristicWeights) -> Result<f64>,
+    mut on_generation: impl FnMut(usize, &HeuristicWeights, f64),
+    rng: &mut impl Rng,
+) -> Result<HeuristicWeights> {
+    if params.population < 2 {
+        bail!("the population must have at least 2 weight vectors");
+    }
+    if !(params.mutation.is_finite() && params.mutation >= 0.0) {
+        bail!("the mutation must be a finite standard deviation, at least 0");
+    }
+    let mut population: Vec<HeuristicWeights> = (0..params.population)
+        .map(|i| {
+            if i == 0 {
+                *initial
+            } else {
+                mutate(initial, params.mutation, rng)
+            }
+        })
+        .collect();
+
+    let mut best = (*initial, f64::MIN);
+    for generation in 0..params.generations {
+        let mut scored: Vec<(HeuristicWeights, f64)> = population
+            .iter()
+            .map(|w| Ok((*w, fitness(w)?)))
+            .collect::<Result<_>>()?;
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+        best = scored[0];
+        on_generation(generation + 1, &best.0, best.1);
+
+        population = scored.iter().take(params.elitism).map(|s| s.0).collect();
+        while population.len() < params.population {
+            let a = tournament(&scored, rng);
+            let b = tournament(&scored, rng);
+            let child = crossover(&a, &b, rng);
+            population.push(mutate(&child, params.mutation, rng));
+        }
+    }
+    Ok(best.0)
+}
+
+/// Share of the points won by the Heuristic engine playing with `weights` against each
+/// opponent, `games` games with each side, a draw being half a point
+pub fn match_fitness(
+    weights: &HeuristicWeights,
+    opponents: &[Engine],
+    games: usize,
+) -> Result<f64> {
+    let data = EngineData {
+        weights: Some(*weights),
+        ..Default::default()
+    };
+    let tc = TimeControl::default();
+    let mut points = 0.0;
+    for opponent in opponents {
+        let r = game::play_match(Engine::Heuristic, *opponent, games, tc, &data, &mut [])?;
+        points += r.0 as f64 + 0.5 * r.2 as f64;
+        let r = game::play_match(*opponent, Engine::Heuristic, games, tc, &data, &mut [])?;
+        points += r.1 as f64 + 0.5 * r.2 as f64;
+    }
+    Ok(points / (2 * games * opponents.len()).max(1) as f64)
+}
+
+/// Evolve the weights with the fitness `match_fitness` against `opponents`
+pub fn evolve_against(
+    initial: &HeuristicWeights,
+    params: &EvolutionParams,
+    opponents: &[Engine],
+    games: usize,
+    on_generation: impl FnMut(usize, &HeuristicWeights, f64),
+    rng: &mut impl Rng,
+) -> Result<HeuristicWeights> {
+    if opponents.contains(&Engine::Human) {
+        bail!("Human can not be an evolution opponent");
+    }
+    let fitness = |w: &HeuristicWeights| match_fitness(w, opponents, games);
+    evolve(initial, params, fitness, on_generation, rng)
+}
+
+/// Best of 3 random individuals
+fn tournament(scored: &[(HeuristicWeights, f64)], rng: &mut impl Rng) -> HeuristicWeights {
+    (0..3)
+        .map(|_| scored[rng.gen_range(0..scored.len())])
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .unwrap()
+        .0
+}
+
+/// Each weight taken from one of the parents
+fn crossover(a: &HeuristicWeights, b: &HeuristicWeights, rng: &mut impl Rng) -> HeuristicWeights {
+    let mut child = [0.0; FEATURES];
+    for (i, w) in child.iter_mut().enumerate() {
+        *w = if rng.gen_bool(0.5) { a.0[i] } else { b.0[i] };
+    }
+    HeuristicWeights(child)
+}
+
+/// Add a gaussian noise to every weight
+fn mutate(weights: &HeuristicWeights, sigma: f64, rng: &mut impl Rng) -> HeuristicWeights {
+    let mut mutated = weights.0;
+    for w in mutated.iter_mut() {
+        // Box-Muller transform
+        let (u1, u2): (f64, f64) = (rng.gen_range(f64::EPSILON..1.0), rng.gen());
+        *w += sigma * (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos();
+    }
+    HeuristicWeights(mutated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn init() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[test]
+    fn test_evolve() {
+        init();
+        let mut rng = StdRng::seed_from_u64(1);
+        let target = [1.0, -2.0, 3.0, -4.0, 5.0, -6.0, 7.0, -8.0, 9.0];
+        let distance = |w: &HeuristicWeights| -> f64 {
+            w.0.iter()
+                .zip(target.iter())
+                .map(|(a, b)| (a - b).powi(2))
+                .sum()
+        };
+        let initial = HeuristicWeights([0.0; FEATURES]);
+        let params = EvolutionParams {
+            population: 30,
+            generations: 60,
+            mutation: 0.5,
+            elitism: 2,
+        };
+        let mut generations = 0;
+        let best = evolve(
+            &initial,
+            &params,
+            |w| Ok(-distance(w)),
+            |_, _, _| generations += 1,
+            &mut rng,
+        )
+        .unwrap();
+        assert_eq!(60, generations);
+        assert!(distance(&best) < distance(&initial) / 10.0);
+
+        let alone = EvolutionParams {
+            population: 1,
+            ..params
+        };
+        let fitness = |w: &HeuristicWeights| Ok(-distance(w));
+        assert!(evolve(&initial, &alone, fitness, |_, _, _| {}, &mut rng).is_err());
+        let diverging = EvolutionParams {
+            mutation: f64::NAN,
+            ..params
+        };
+        assert!(evolve(&initial, &diverging, fitness, |_, _, _| {}, &mut rng).is_err());
+        let failing = |_: &HeuristicWeights| Err(anyhow::anyhow!("no fitness"));
+        assert!(evolve(&initial, &params, failing, |_, _, _| {}, &mut rng).is_err());
+    }
+
+    #[test]
+    fn test_match_fitness() {
+        init();
+        let weights = HeuristicWeights::default();
+        assert_eq!(0.5, match_fitness(&weights, &[Engine::Rules], 2).unwrap());
+        assert_eq!(0.0, match_fitness(&weights, &[], 2).unwrap());
+
+        let params = EvolutionParams {
+            population: 2,
+            generations: 1,
+            ..Default::default()
+        };
+        let mut rng = StdRng::seed_from_u64(1);
+        let opponents = [Engine::Rules, Engine::Human];
+        let evolved = evolve_against(&weights, &params, &opponents, 1, |_, _, _| {}, &mut rng);
+        assert!(evolved.is_err());
+        let evolved = evolve_against(
+            &weights,
+            &params,
+            &opponents[..1],
+            1,
+            |_, _, _| {},
+            &mut rng,
+        );
+        assert!(evolved.is_ok());
+    }
+}
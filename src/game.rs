@@ -1,41 +1,226 @@
+/// Result of the engines and of the game loop
 pub type Result<T> = anyhow::Result<T>;
+use anyhow::bail;
 use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Instant;
 
-use crate::board::Board;
+use crate::ai::{EngineMove, SearchLimits, TieBreak};
+use crate::board::{Board, Move};
+use crate::clock::{Clock, TimeControl};
+use crate::heuristic::{self, HeuristicWeights};
+use crate::menace::{self, Menace};
+use crate::nn::{self, Network};
+use crate::observer::{EndReason, GameObserver, MoveEvent};
+use crate::qlearning::{self, QTable};
+use crate::rules;
+use crate::tablebase::{self, Tablebase};
 use crate::{ai, player};
 use crate::{board, player::Player};
 use log::info;
 
+/// Serialized by serde as its variant name, like "Rules", or as an object for the variants
+/// with a parameter, like `{"MinMax": "First"}` or `{"Level": 7}`. Deserialized from these
+/// shapes or from the names of `engine_from_name`, like "MinMax" or "Level7", levels outside
+/// 1 to 10 being rejected.
 #[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "SerdeEngine"))]
+#[non_exhaustive]
 pub enum Engine {
+    /// Moves typed at the terminal
     Human,
+    /// Random legal move
     RandomMove,
+    /// Winning move if any, random otherwise
     WinningMove,
+    /// Winning move if any, else a move blocking the opponent, random otherwise
     WinningAndNotLosingMove,
-    MinMax,
+    /// Minimax best move, the tie break choosing between moves of the same score
+    MinMax(TieBreak),
+    /// Minimax playing the best move with probability level / 10 (1 to 10)
+    Level(u8),
+    /// Best move read from the tablebase, or searched if missing
+    Tablebase,
+    /// Best move learned by Q-learning
+    QLearning,
+    /// Move drawn from MENACE matchboxes
+    Menace,
+    /// Most probable move of the neural network
+    Neural,
+    /// Best move according to weighted heuristic features
+    Heuristic,
+    /// Move of the first Newell and Simon rule that applies
+    Rules,
 }
 
-pub fn play_game(player_x_engine: Engine, player_o_engine: Engine) -> Option<Player> {
+/// Engine as deserialized, before the check of its name or level
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum SerdeEngine {
+    Name(String),
+    Variant(EngineVariant),
+}
+
+/// Variants of `Engine` with a parameter
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+enum EngineVariant {
+    MinMax(TieBreak),
+    Level(u8),
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<SerdeEngine> for Engine {
+    type Error = String;
+
+    fn try_from(engine: SerdeEngine) -> std::result::Result<Self, Self::Error> {
+        match engine {
+            SerdeEngine::Name(name) => {
+                engine_from_name(&name).ok_or_else(|| format!("unknown engine {}", name))
+            }
+            SerdeEngine::Variant(EngineVariant::MinMax(tie_break)) => Ok(Engine::MinMax(tie_break)),
+            SerdeEngine::Variant(EngineVariant::Level(level)) => (1..=10)
+                .contains(&level)
+                .then_some(Engine::Level(level))
+                .ok_or_else(|| format!("level {} is not between 1 and 10", level)),
+        }
+    }
+}
+
+/// Data loaded or learned by the engines, kept between games
+#[derive(Debug, Default)]
+pub struct EngineData {
+    /// best moves of the Tablebase engine, searched if None
+    pub tablebase: Option<Tablebase>,
+    /// values learned by the QLearning engine, random moves if None
+    pub qtable: Option<QTable>,
+    /// matchboxes of the Menace engine, reinforced between games by a `MenaceObserver`
+    pub menace: Option<Mutex<Menace>>,
+    /// network of the Neural engine, random moves if None
+    pub network: Option<Network>,
+    /// weights of the Heuristic engine, the default ones if None
+    pub weights: Option<HeuristicWeights>,
+}
+
+impl EngineData {
+    /// Whether `engine` plays from loaded data, and not from its fallback (a search or random
+    /// moves) when its data is missing
+    pub fn has_data_for(&self, engine: &Engine) -> bool {
+        match engine {
+            Engine::Tablebase => self.tablebase.is_some(),
+            Engine::QLearning => self.qtable.is_some(),
+            Engine::Menace => self.menace.is_some(),
+            Engine::Neural => self.network.is_some(),
+            _ => true,
+        }
+    }
+}
+
+/// Moves played during a game, X first, and its winner (None for a draw)
+#[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GameRecord {
+    /// moves in the order they were played
+    #[cfg_attr(feature = "serde", serde(with = "crate::board::serde_moves"))]
+    pub moves: Vec<Move>,
+    /// None for a draw
+    pub winner: Option<Player>,
+}
+
+/// Play a game, notifying every observer of its start, of each move and of its end. The
+/// moves of a human are given by the first observer answering `human_move`, or read on the
+/// standard input. Fail when an engine can't give a move (like a human without input or an engine without
+/// its data) or gives an illegal one, the game being left unfinished.
+pub fn play_game(
+    player_x_engine: Engine,
+    player_o_engine: Engine,
+    time_control: TimeControl,
+    data: &EngineData,
+    observers: &mut [&mut dyn GameObserver],
+) -> Result<GameRecord> {
     info!("Launching a new game");
 
     let mut board: Board = board::generate_new_board();
-    let mut output = board::render_board(&board).unwrap();
-    println!("{}", output);
+    for observer in observers.iter_mut() {
+        observer.on_game_started(player_x_engine, player_o_engine, &board);
+    }
 
     let mut full_cases: u8 = 0;
     let mut active_player = Player::PlayerX;
     let mut active_engine = player_x_engine;
 
+    let mut record = GameRecord::default();
     let mut cache: HashMap<u64, i8> = HashMap::new();
+    let mut clock_x = Clock::new(time_control);
+    let mut clock_o = Clock::new(time_control);
 
     while full_cases < 9 {
-        board = play_move(&board, &active_player, &active_engine, &mut cache).unwrap();
+        let clock = match active_player {
+            Player::PlayerX => &mut clock_x,
+            Player::PlayerO => &mut clock_o,
+        };
+        // the deadline is not before the end of the budget counted by the clock
+        let start = Instant::now();
+        let limits = SearchLimits::from_budget(clock.move_budget());
+        let human_move = match active_engine {
+            Engine::Human => observers
+                .iter_mut()
+                .find_map(|o| o.human_move(&board, &active_player, limits.deadline)),
+            _ => None,
+        };
+        let engine_move = match human_move {
+            Some(m) => m.map(EngineMove::from),
+            None => play_move(
+                &board,
+                &active_player,
+                &active_engine,
+                data,
+                &mut cache,
+                &limits,
+            ),
+        };
+        let elapsed = start.elapsed();
+        if !clock.punch(elapsed) {
+            info!("{:?} lost on time", active_player);
+            record.winner = Some(switch_player(&active_player));
+            for observer in observers.iter_mut() {
+                observer.on_game_ended(&record, EndReason::Time);
+            }
+            return Ok(record);
+        }
+        if let Some(remaining) = clock.remaining() {
+            info!("{:?} has {:?} left", active_player, remaining);
+        }
+        let engine_move = engine_move?;
+        info!(
+            "{:?} ({:?}) plays {:?} - score {:?} - pv {:?}",
+            active_player, active_engine, engine_move.coord, engine_move.score, engine_move.pv
+        );
+        board = board::make_move(&board, engine_move.coord, &active_player)?;
+        record.moves.push(engine_move.coord);
         full_cases += 1;
-        output = board::render_board(&board).unwrap();
-        println!("{}", output);
+        let event = MoveEvent {
+            player: active_player,
+            engine: active_engine,
+            engine_move,
+            board,
+            elapsed,
+            remaining: clock.remaining(),
+        };
+        for observer in observers.iter_mut() {
+            observer.on_move_played(&event);
+        }
 
         match board::is_move_win(&board) {
-            Some(p) => return Some(p),
+            Some(p) => {
+                record.winner = Some(p);
+                for observer in observers.iter_mut() {
+                    observer.on_game_ended(&record, EndReason::Line);
+                }
+                return Ok(record);
+            }
             None => {
                 active_player = switch_player(&active_player);
                 if active_engine == player_x_engine {
@@ -46,26 +231,128 @@ pub fn play_game(player_x_engine: Engine, player_o_engine: Engine) -> Option<Pla
             }
         }
     }
-    None
+    for observer in observers.iter_mut() {
+        observer.on_game_ended(&record, EndReason::Draw);
+    }
+    Ok(record)
+}
+
+/// Play `games` games and return the number of wins of X, of O and of draws. Stop at the
+/// first game failing, as described by `play_game`.
+pub fn play_match(
+    engine_x: Engine,
+    engine_o: Engine,
+    games: usize,
+    time_control: TimeControl,
+    data: &EngineData,
+    observers: &mut [&mut dyn GameObserver],
+) -> Result<(usize, usize, usize)> {
+    let mut results: (usize, usize, usize) = (0, 0, 0);
+    for _i in 0..games {
+        let record = play_game(engine_x, engine_o, time_control, data, observers)?;
+        match record.winner {
+            Some(Player::PlayerX) => results.0 += 1,
+            Some(Player::PlayerO) => results.1 += 1,
+            None => results.2 += 1,
+        }
+    }
+    Ok(results)
 }
 
 fn play_move(
     board: &Board,
     active_player: &Player,
     engine: &Engine,
+    data: &EngineData,
     cache: &mut HashMap<u64, i8>,
-) -> Result<Board> {
+    limits: &SearchLimits,
+) -> Result<EngineMove> {
     match engine {
-        Engine::Human => player::human_get_move(board, active_player),
-        Engine::RandomMove => ai::random_ai(board, active_player),
+        Engine::Human => player::human_get_move(board, active_player, limits.deadline, data)
+            .map(EngineMove::from),
+        Engine::RandomMove => ai::random_ai(board),
         Engine::WinningMove => ai::finds_winning_moves_ai(board, active_player),
         Engine::WinningAndNotLosingMove => {
             ai::finds_winning_and_not_losing_moves_ai(board, active_player)
         }
-        Engine::MinMax => ai::minimax_algo_ai(board, active_player, cache),
+        Engine::MinMax(tie_break) => {
+            ai::minimax_algo_ai(board, active_player, cache, limits, *tie_break)
+        }
+        Engine::Level(level) => ai::level_ai(board, active_player, *level, cache, limits),
+        Engine::Tablebase => {
+            tablebase::tablebase_ai(board, active_player, data.tablebase.as_ref(), cache, limits)
+        }
+        Engine::QLearning => qlearning::qlearning_ai(board, data.qtable.as_ref()),
+        Engine::Menace => {
+            let menace = data.menace.as_ref().map(|m| m.lock().unwrap());
+            menace::menace_ai(board, menace.as_deref())
+        }
+        Engine::Neural => nn::neural_ai(board, data.network.as_ref()),
+        Engine::Heuristic => {
+            heuristic::heuristic_ai(board, active_player, &data.weights.unwrap_or_default())
+        }
+        Engine::Rules => rules::rules_ai(board, active_player),
     }
 }
 
+/// Names of the engines (as given on the command line), without the levels
+const NAMED_ENGINES: [(&str, Engine); 11] = [
+    ("Human", Engine::Human),
+    ("RandomMove", Engine::RandomMove),
+    ("WinningMove", Engine::WinningMove),
+    ("WinningAndNotLosingMove", Engine::WinningAndNotLosingMove),
+    ("MinMax", Engine::MinMax(TieBreak::First)),
+    ("Tablebase", Engine::Tablebase),
+    ("QLearning", Engine::QLearning),
+    ("Menace", Engine::Menace),
+    ("Neural", Engine::Neural),
+    ("Heuristic", Engine::Heuristic),
+    ("Rules", Engine::Rules),
+];
+
+/// Registry of the names accepted by `engine_from_name`
+pub fn engine_names() -> Vec<String> {
+    let mut names: Vec<String> = NAMED_ENGINES
+        .iter()
+        .map(|(name, _)| name.to_string())
+        .collect();
+    names.extend((1..=10).map(|level| format!("Level{}", level)));
+    names
+}
+
+/// Return the engine with this name (as given on the command line), None if unknown
+pub fn engine_from_name(name: &str) -> Option<Engine> {
+    if let Some(level) = name.strip_prefix("Level") {
+        let level: u8 = level.parse().ok()?;
+        return (1..=10).contains(&level).then_some(Engine::Level(level));
+    }
+    NAMED_ENGINES
+        .iter()
+        .find(|(engine_name, _)| *engine_name == name)
+        .map(|(_, engine)| *engine)
+}
+
+/// Ask an engine for its move outside of a game, without time limit
+pub fn ask_engine(
+    board: &Board,
+    player: &Player,
+    engine: &Engine,
+    data: &EngineData,
+) -> Result<EngineMove> {
+    if *engine == Engine::Human {
+        bail!("a human engine can't be asked for a move");
+    }
+    play_move(
+        board,
+        player,
+        engine,
+        data,
+        &mut HashMap::new(),
+        &SearchLimits::default(),
+    )
+}
+
+/// Opponent of `active_player`
 pub fn switch_player(active_player: &Player) -> Player {
     match active_player {
         Player::PlayerX => Player::PlayerO,
@@ -76,6 +363,7 @@ pub fn switch_player(active_player: &Player) -> Player {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::time::Duration;
     fn init() {
         let _ = env_logger::builder().is_test(true).try_init();
     }
@@ -99,21 +387,244 @@ mod tests {
         ];
         let engine = Engine::RandomMove;
         let mut cache: HashMap<u64, i8> = HashMap::new();
-        let new_board = play_move(&board, &active_player, &engine, &mut cache).unwrap();
-        assert!(
-            new_board[0][1] == Some('X')
-                || new_board[1][0] == Some('X')
-                || new_board[2][1] == Some('X')
-        );
+        let limits = SearchLimits::default();
+        let data = EngineData::default();
+        let m = play_move(&board, &active_player, &engine, &data, &mut cache, &limits).unwrap();
+        assert!(m.coord == (0, 1) || m.coord == (1, 0) || m.coord == (2, 1));
+        let new_board = board::make_move(&board, m.coord, &active_player).unwrap();
         cache.clear();
 
-        let engine = Engine::MinMax;
+        let engine = Engine::MinMax(TieBreak::First);
         let active_player = Player::PlayerO;
-        let new_new_board = play_move(&new_board, &active_player, &engine, &mut cache).unwrap();
-        assert!(
-            new_new_board[0][1] == Some('O')
-                || new_new_board[1][0] == Some('O')
-                || new_new_board[2][1] == Some('O')
+        let m = play_move(
+            &new_board,
+            &active_player,
+            &engine,
+            &data,
+            &mut cache,
+            &limits,
+        )
+        .unwrap();
+        assert!(m.coord == (0, 1) || m.coord == (1, 0) || m.coord == (2, 1));
+        assert!(board::is_valid_move(&new_board, m.coord));
+    }
+
+    #[test]
+    fn test_play_game_lost_on_time() {
+        init();
+        let time_control = TimeControl {
+            base: None,
+            increment: Duration::ZERO,
+            per_move: Some(Duration::ZERO),
+        };
+        let record = play_game(
+            Engine::MinMax(TieBreak::First),
+            Engine::MinMax(TieBreak::First),
+            time_control,
+            &EngineData::default(),
+            &mut [],
+        )
+        .unwrap();
+        assert_eq!(Some(Player::PlayerO), record.winner);
+        assert!(record.moves.is_empty());
+
+        let record = play_game(
+            Engine::MinMax(TieBreak::First),
+            Engine::MinMax(TieBreak::First),
+            TimeControl::default(),
+            &EngineData::default(),
+            &mut [],
+        )
+        .unwrap();
+        assert_eq!(None, record.winner);
+        assert_eq!(9, record.moves.len());
+    }
+
+    #[derive(Default)]
+    struct EventCounter {
+        started: usize,
+        moves: usize,
+        ended: Vec<EndReason>,
+    }
+
+    impl GameObserver for EventCounter {
+        fn on_game_started(&mut self, _engine_x: Engine, _engine_o: Engine, _board: &Board) {
+            self.started += 1;
+        }
+
+        fn on_move_played(&mut self, _event: &MoveEvent) {
+            self.moves += 1;
+        }
+
+        fn on_game_ended(&mut self, _record: &GameRecord, reason: EndReason) {
+            self.ended.push(reason);
+        }
+    }
+
+    #[test]
+    fn test_play_game_observers() {
+        init();
+        let mut first = EventCounter::default();
+        let mut second = EventCounter::default();
+        let record = play_game(
+            Engine::Rules,
+            Engine::Rules,
+            TimeControl::default(),
+            &EngineData::default(),
+            &mut [&mut first, &mut second],
+        )
+        .unwrap();
+        assert_eq!(None, record.winner);
+        for counter in [first, second] {
+            assert_eq!(1, counter.started);
+            assert_eq!(9, counter.moves);
+            assert_eq!(vec![EndReason::Draw], counter.ended);
+        }
+    }
+
+    /// Human player answering the moves of a list, then failing
+    struct ScriptedHuman(Vec<Move>);
+
+    impl GameObserver for ScriptedHuman {
+        fn human_move(
+            &mut self,
+            _board: &Board,
+            _player: &Player,
+            _deadline: Option<Instant>,
+        ) -> Option<Result<Move>> {
+            if self.0.is_empty() {
+                Some(Err(anyhow::anyhow!("no more moves")))
+            } else {
+                Some(Ok(self.0.remove(0)))
+            }
+        }
+    }
+
+    #[test]
+    fn test_play_game_human_moves() {
+        init();
+        let data = EngineData::default();
+        let tc = TimeControl::default();
+        let mut human = ScriptedHuman(vec![(0, 0), (2, 2), (0, 2), (1, 0)]);
+        let mut counter = EventCounter::default();
+        let record = play_game(
+            Engine::Human,
+            Engine::Rules,
+            tc,
+            &data,
+            &mut [&mut counter, &mut human],
+        )
+        .unwrap();
+        assert_eq!(Some(Player::PlayerO), record.winner);
+        assert_eq!(vec![EndReason::Line], counter.ended);
+
+        // the move of the center is already taken by Rules
+        let mut counter = EventCounter::default();
+        let mut human = ScriptedHuman(vec![(0, 0), (1, 1)]);
+        let played = play_game(
+            Engine::Human,
+            Engine::Rules,
+            tc,
+            &data,
+            &mut [&mut counter, &mut human],
+        );
+        assert!(played.is_err());
+        assert_eq!(2, counter.moves);
+        assert!(counter.ended.is_empty());
+        let mut human = ScriptedHuman(Vec::new());
+        assert!(play_game(Engine::Rules, Engine::Human, tc, &data, &mut [&mut human]).is_err());
+
+        let no_time = TimeControl {
+            base: None,
+            increment: Duration::ZERO,
+            per_move: Some(Duration::ZERO),
+        };
+        let mut human = ScriptedHuman(Vec::new());
+        let record = play_game(
+            Engine::Human,
+            Engine::Rules,
+            no_time,
+            &data,
+            &mut [&mut human],
+        );
+        assert_eq!(Some(Player::PlayerO), record.unwrap().winner);
+    }
+
+    #[test]
+    fn test_play_match() {
+        init();
+        let mut counter = EventCounter::default();
+        let results = play_match(
+            Engine::Rules,
+            Engine::RandomMove,
+            3,
+            TimeControl::default(),
+            &EngineData::default(),
+            &mut [&mut counter],
+        )
+        .unwrap();
+        assert_eq!(0, results.1);
+        assert_eq!(3, results.0 + results.2);
+        assert_eq!(3, counter.started);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_json_shape() {
+        use serde_json::json;
+        init();
+        let record = GameRecord {
+            moves: vec![(1, 1), (0, 2)],
+            winner: Some(Player::PlayerO),
+        };
+        let value = serde_json::to_value(&record).unwrap();
+        assert_eq!(json!({"moves": [[1, 1], [0, 2]], "winner": "O"}), value);
+        assert_eq!(record, serde_json::from_value(value).unwrap());
+        let outside = json!({"moves": [[3, 0]], "winner": null});
+        assert!(serde_json::from_value::<GameRecord>(outside).is_err());
+
+        let engines = [
+            Engine::Rules,
+            Engine::MinMax(TieBreak::Preferred),
+            Engine::Level(7),
+        ];
+        let value = serde_json::to_value(engines).unwrap();
+        assert_eq!(
+            json!(["Rules", {"MinMax": "Preferred"}, {"Level": 7}]),
+            value
+        );
+        assert_eq!(
+            engines,
+            serde_json::from_value::<[Engine; 3]>(value).unwrap()
+        );
+        let names = json!(["MinMax", "Level7"]);
+        assert_eq!(
+            [Engine::MinMax(TieBreak::First), Engine::Level(7)],
+            serde_json::from_value::<[Engine; 2]>(names).unwrap()
+        );
+        for invalid in [json!({"Level": 200}), json!("Level11"), json!("Z")] {
+            assert!(serde_json::from_value::<Engine>(invalid).is_err());
+        }
+    }
+
+    #[test]
+    fn test_engine_from_name() {
+        init();
+        assert_eq!(Some(Engine::Human), engine_from_name("Human"));
+        assert_eq!(
+            Some(Engine::MinMax(TieBreak::First)),
+            engine_from_name("MinMax")
         );
+        assert_eq!(Some(Engine::Level(7)), engine_from_name("Level7"));
+        assert_eq!(None, engine_from_name("Level11"));
+        assert_eq!(None, engine_from_name("Unknown"));
+        for name in engine_names() {
+            assert!(engine_from_name(&name).is_some());
+        }
+        assert_eq!(21, engine_names().len());
+
+        let data = EngineData::default();
+        assert!(!data.has_data_for(&Engine::Tablebase));
+        assert!(data.has_data_for(&Engine::Heuristic));
     }
 }
@@ -9,6 +9,7 @@ struct Position<'a> {
 }
 
 impl Position<'_> {
+    #[allow(clippy::char_lit_as_u8)]
     fn bytes(&self) -> Vec<u8> {
         let mut b: Vec<u8> = Vec::new();
         for r in self.board.iter() {
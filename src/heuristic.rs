@@ -0,0 +1,208 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Result};
+use rand::Rng;
+
+use crate::ai::{self, EngineMove};
+use crate::board::{self, Board, Move};
+use crate::player::Player;
+use crate::training::Model;
+
+/// Number of features, and of weights
+pub const FEATURES: usize = 9;
+/// Names of the features, in the order of their weights
+pub const FEATURE_NAMES: [&str; FEATURES] = [
+    "win",
+    "own threats",
+    "own forks",
+    "opponent threats",
+    "own open lines",
+    "opponent open lines",
+    "center",
+    "corners",
+    "sides",
+];
+
+const LINES: [[(usize, usize); 3]; 8] = [
+    [(0, 0), (0, 1), (0, 2)],
+    [(1, 0), (1, 1), (1, 2)],
+    [(2, 0), (2, 1), (2, 2)],
+    [(0, 0), (1, 0), (2, 0)],
+    [(0, 1), (1, 1), (2, 1)],
+    [(0, 2), (1, 2), (2, 2)],
+    [(0, 0), (1, 1), (2, 2)],
+    [(0, 2), (1, 1), (2, 0)],
+];
+
+/// Weight of each feature (see `FEATURE_NAMES`) in the evaluation of a move
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct HeuristicWeights(pub [f64; FEATURES]);
+
+impl Default for HeuristicWeights {
+    fn default() -> Self {
+        HeuristicWeights([100.0, 2.0, 10.0, -50.0, 1.0, -1.0, 1.5, 1.0, 0.5])
+    }
+}
+
+impl HeuristicWeights {
+    /// Text format : the weights on one line
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let weights: Vec<String> = self.0.iter().map(|w| w.to_string()).collect();
+        fs::write(path, weights.join(" ") + "\n")?;
+        Ok(())
+    }
+
+    /// Read weights written by `save`, which must be finite
+    pub fn load(path: &Path) -> Result<HeuristicWeights> {
+        let weights: Vec<f64> = fs::read_to_string(path)?
+            .split_whitespace()
+            .map(|w| w.parse())
+            .collect::<Result<_, _>>()?;
+        if weights.len() != FEATURES {
+            bail!("{} weights expected, {} found", FEATURES, weights.len());
+        }
+        if weights.iter().any(|w| !w.is_finite()) {
+            bail!("Weight not finite");
+        }
+        Ok(HeuristicWeights(weights.try_into().unwrap()))
+    }
+}
+
+impl Model for HeuristicWeights {
+    fn load(path: &Path) -> Result<HeuristicWeights> {
+        HeuristicWeights::load(path)
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        HeuristicWeights::save(self, path)
+    }
+
+    fn summary(&self) -> String {
+        format!("{} weights", FEATURES)
+    }
+}
+
+/// Features of a position after a move of `player`
+pub fn features(board: &Board, player: &Player) -> [f64; FEATURES] {
+    let (own, other) = match player {
+        Player::PlayerX => (Some('X'), Some('O')),
+        Player::PlayerO => (Some('O'), Some('X')),
+    };
+    let mut f = [0.0; FEATURES];
+    if board::is_move_win(board) == Some(*player) {
+        f[0] = 1.0;
+    }
+    let mut own_threats = 0.0;
+    for line in LINES {
+        let cases = line.map(|(y, x)| board[y][x]);
+        let own_count = cases.iter().filter(|c| **c == own).count();
+        let other_count = cases.iter().filter(|c| **c == other).count();
+        match (own_count, other_count) {
+            (2, 0) => own_threats += 1.0,
+            (0, 2) => f[3] += 1.0,
+            _ => {}
+        }
+        if own_count > 0 && other_count == 0 {
+            f[4] += 1.0;
+        }
+        if other_count > 0 && own_count == 0 {
+            f[5] += 1.0;
+        }
+    }
+    f[1] = own_threats;
+    if own_threats >= 2.0 {
+        f[2] = 1.0;
+    }
+    for (y, line) in board.iter().enumerate() {
+        for (x, c) in line.iter().enumerate() {
+            if *c != own {
+                continue;
+            }
+            match (y, x) {
+                (1, 1) => f[6] += 1.0,
+                (y, x) if y != 1 && x != 1 => f[7] += 1.0,
+                _ => f[8] += 1.0,
+            }
+        }
+    }
+    f
+}
+
+/// Play the move whose resulting position has the best weighted features, a random one
+/// between equal evaluations
+pub fn heuristic_ai(
+    board: &Board,
+    player: &Player,
+    weights: &HeuristicWeights,
+) -> Result<EngineMove> {
+    let evaluations: Vec<(f64, Move)> = ai::find_all_legal_moves(board)
+        .into_iter()
+        .map(|m| {
+            let new_board = board::make_move(board, m, player).unwrap();
+            let f = features(&new_board, player);
+            let evaluation: f64 = f.iter().zip(weights.0.iter()).map(|(f, w)| f * w).sum();
+            (evaluation, m)
+        })
+        .collect();
+    let best = evaluations
+        .iter()
+        .map(|e| e.0)
+        .max_by(f64::total_cmp)
+        .ok_or_else(|| anyhow!("no legal move available"))?;
+    let best_moves: Vec<Move> = evaluations
+        .iter()
+        .filter(|e| e.0.total_cmp(&best).is_eq())
+        .map(|e| e.1)
+        .collect();
+    let m = best_moves[rand::thread_rng().gen_range(0..best_moves.len())];
+    Ok(m.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn init() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[test]
+    fn test_features() {
+        init();
+        let b = board::parse_board("X.X/.O./X.O").unwrap();
+        let f = features(&b, &Player::PlayerX);
+        assert_eq!([0.0, 2.0, 1.0, 0.0, 2.0, 2.0, 0.0, 3.0, 0.0], f);
+        let f = features(&b, &Player::PlayerO);
+        assert_eq!(2.0, f[3]);
+        assert_eq!(1.0, f[6]);
+    }
+
+    #[test]
+    fn test_heuristic_ai() {
+        init();
+        let weights = HeuristicWeights::default();
+        // win first, then block
+        let b = board::parse_board("XX./OO./...").unwrap();
+        assert_eq!(
+            (0, 2),
+            heuristic_ai(&b, &Player::PlayerX, &weights).unwrap().coord
+        );
+        let b = board::parse_board("XX./O../O..").unwrap();
+        assert_eq!(
+            (0, 2),
+            heuristic_ai(&b, &Player::PlayerO, &weights).unwrap().coord
+        );
+
+        let path = std::env::temp_dir().join("tictactoe_test_weights.txt");
+        weights.save(&path).unwrap();
+        assert_eq!(weights, HeuristicWeights::load(&path).unwrap());
+        fs::write(&path, "100 2 10 -50 1 -1 NaN 1 0.5\n").unwrap();
+        assert!(HeuristicWeights::load(&path).is_err());
+        fs::remove_file(path).unwrap();
+
+        let nan = HeuristicWeights([f64::NAN; FEATURES]);
+        let b = board::generate_new_board();
+        assert!(heuristic_ai(&b, &Player::PlayerX, &nan).is_ok());
+    }
+}
@@ -0,0 +1,318 @@
+use std::collections::HashMap;
+use std::io::Write;
+
+use anyhow::{anyhow, Result};
+use log::info;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Response, Server};
+
+use crate::ai;
+use crate::board::{self, Board, Move};
+use crate::game::{self, Engine, EngineData};
+
+/// A game played through the API
+#[derive(Debug, PartialEq, Clone)]
+struct ApiGame {
+    board: Board,
+    moves: Vec<Move>,
+}
+
+/// Games of the API server and the data of its engines
+#[derive(Debug, Default)]
+pub struct Api {
+    games: HashMap<u64, ApiGame>,
+    next_id: u64,
+    data: EngineData,
+}
+
+/// Error answered with an HTTP status and a JSON body `{"error": message}`
+struct ApiError(u16, String);
+
+impl From<anyhow::Error> for ApiError {
+    fn from(e: anyhow::Error) -> Self {
+        ApiError(400, e.to_string())
+    }
+}
+
+/// Serve the API on `address` until the process is stopped. Routes :
+/// - `POST /games` : create a game
+/// - `GET /games/{id}` : state of a game
+/// - `POST /games/{id}/moves` : play `{"move": [2, 1]}`, or the move of `{"engine": "Rules"}`
+///   (the engine names of the command line, like "MinMax" or "Level7")
+/// - `POST /games/{id}/hint` : move of `{"engine": "Rules"}` without playing it
+/// - `POST /evaluate` : minimax value of `{"board": [["X", null, "O"], ...]}` and of its legal
+///   moves
+///
+/// Boards, moves, players and engines have the JSON shape of the `serde` feature.
+///
+/// The address listened on is printed to `out`.
+pub fn serve(address: &str, data: EngineData, out: &mut impl Write) -> Result<()> {
+    let server = Server::http(address).map_err(|e| anyhow!("{}", e))?;
+    writeln!(out, "API listening on http://{}", server.server_addr())?;
+    let mut api = Api {
+        data,
+        ..Default::default()
+    };
+    for mut request in server.incoming_requests() {
+        let mut body = String::new();
+        let (status, value) = match request.as_reader().read_to_string(&mut body) {
+            Ok(_) if *request.method() == Method::Options => (204, Value::Null),
+            Ok(_) => api.handle(request.method().as_str(), request.url(), &body),
+            Err(e) => (400, json!({ "error": e.to_string() })),
+        };
+        info!("{} {} : {}", request.method(), request.url(), status);
+        let response = Response::from_string(value.to_string())
+            .with_status_code(status)
+            .with_header(header("Content-Type", "application/json"))
+            .with_header(header("Access-Control-Allow-Origin", "*"))
+            .with_header(header("Access-Control-Allow-Headers", "Content-Type"));
+        if let Err(e) = request.respond(response) {
+            info!("Error answering a request : {}", e);
+        }
+    }
+    Ok(())
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).unwrap()
+}
+
+impl Api {
+    /// Answer a request with an HTTP status and a JSON body
+    pub fn handle(&mut self, method: &str, url: &str, body: &str) -> (u16, Value) {
+        let path: Vec<&str> = url
+            .split('?')
+            .next()
+            .unwrap_or_default()
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .collect();
+        let result = match (method, path.as_slice()) {
+            ("POST", ["games"]) => Ok(self.create_game()),
+            ("GET", ["games", id]) => self.game(id).map(|(id, g)| (200, game_to_json(id, g))),
+            ("POST", ["games", id, "moves"]) => self.play(id, body),
+            ("POST", ["games", id, "hint"]) => self.hint(id, body),
+            ("POST", ["evaluate"]) => evaluate(body),
+            _ => Err(ApiError(404, format!("no route for {} {}", method, url))),
+        };
+        match result {
+            Ok(answer) => answer,
+            Err(ApiError(status, message)) => (status, json!({ "error": message })),
+        }
+    }
+
+    fn create_game(&mut self) -> (u16, Value) {
+        self.next_id += 1;
+        let game = ApiGame {
+            board: board::generate_new_board(),
+            moves: Vec::new(),
+        };
+        let value = game_to_json(self.next_id, &game);
+        self.games.insert(self.next_id, game);
+        (201, value)
+    }
+
+    fn game(&self, id: &str) -> Result<(u64, &ApiGame), ApiError> {
+        let id: u64 = id
+            .parse()
+            .map_err(|_| ApiError(404, format!("no game {}", id)))?;
+        match self.games.get(&id) {
+            Some(game) => Ok((id, game)),
+            None => Err(ApiError(404, format!("no game {}", id))),
+        }
+    }
+
+    /// Play the move of the body, or the move of the engine it names
+    fn play(&mut self, id: &str, body: &str) -> Result<(u16, Value), ApiError> {
+        let (id, game) = self.game(id)?;
+        if is_over(&game.board) {
+            return Err(ApiError(409, "the game is over".to_owned()));
+        }
+        let request = parse_body(body)?;
+        let player = board::player_to_move(&game.board);
+        let m = match request.get("engine") {
+            Some(_) => self.engine_move(&game.board, &request)?.coord,
+            None => {
+                let m = parse_field(&request, "move", |v| board::serde_move::deserialize(v))?;
+                if !board::is_valid_move(&game.board, m) {
+                    return Err(ApiError(
+                        400,
+                        format!("{} is already taken", board::format_move(m)),
+                    ));
+                }
+                m
+            }
+        };
+        let game = self.games.get_mut(&id).unwrap();
+        game.board = board::make_move(&game.board, m, &player)?;
+        game.moves.push(m);
+        Ok((200, game_to_json(id, game)))
+    }
+
+    /// Move of an engine, not played
+    fn hint(&self, id: &str, body: &str) -> Result<(u16, Value), ApiError> {
+        let (_, game) = self.game(id)?;
+        if is_over(&game.board) {
+            return Err(ApiError(409, "the game is over".to_owned()));
+        }
+        let engine_move = self.engine_move(&game.board, &parse_body(body)?)?;
+        Ok((200, json!(engine_move)))
+    }
+
+    /// Move of the engine of the request, MinMax if it names none
+    fn engine_move(&self, board: &Board, request: &Value) -> Result<ai::EngineMove, ApiError> {
+        let engine = match request.get("engine") {
+            Some(_) => parse_field(request, "engine", |v| Engine::deserialize(v))?,
+            None => Engine::MinMax(ai::TieBreak::First),
+        };
+        let player = board::player_to_move(board);
+        Ok(game::ask_engine(board, &player, &engine, &self.data)?)
+    }
+}
+
+/// Minimax value of the position and outcome of every legal move
+fn evaluate(body: &str) -> Result<(u16, Value), ApiError> {
+    let request = parse_body(body)?;
+    let b = parse_field(&request, "board", |v| board::serde_board::deserialize(v))?;
+    board::check_reachable(&b)?;
+    let player = board::player_to_move(&b);
+    let empty_cases = ai::find_all_legal_moves(&b).len();
+    let mut cache = HashMap::new();
+    let value = ai::minimax_value(&b, &mut cache);
+    let moves: Vec<Value> = if is_over(&b) {
+        Vec::new()
+    } else {
+        ai::minimax_scores(&b, &player, &mut cache)
+            .into_iter()
+            .map(|(score, m)| {
+                json!({
+                    "move": m,
+                    "score": score,
+                    "outcome": ai::outcome(score, &player, empty_cases).to_string(),
+                })
+            })
+            .collect()
+    };
+    Ok((
+        200,
+        json!({
+            "board": b,
+            "to_move": player,
+            "value": value,
+            "moves": moves,
+        }),
+    ))
+}
+
+fn is_over(board: &Board) -> bool {
+    board::is_move_win(board).is_some() || board::is_board_full(board)
+}
+
+fn parse_body(body: &str) -> Result<Value, ApiError> {
+    if body.trim().is_empty() {
+        return Ok(json!({}));
+    }
+    serde_json::from_str(body).map_err(|e| ApiError(400, format!("invalid JSON : {}", e)))
+}
+
+/// Field `name` of the request, parsed by `deserialize`
+fn parse_field<T>(
+    request: &Value,
+    name: &str,
+    deserialize: impl FnOnce(&Value) -> serde_json::Result<T>,
+) -> Result<T, ApiError> {
+    deserialize(&request[name]).map_err(|e| ApiError(400, format!("invalid {} : {}", name, e)))
+}
+
+fn game_to_json(id: u64, game: &ApiGame) -> Value {
+    json!({
+        "id": id,
+        "board": game.board,
+        "to_move": board::player_to_move(&game.board),
+        "moves": game.moves,
+        "over": is_over(&game.board),
+        "winner": board::is_move_win(&game.board),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn init() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[test]
+    fn test_game_routes() {
+        init();
+        let mut api = Api::default();
+        let (status, game) = api.handle("POST", "/games", "");
+        assert_eq!(201, status);
+        assert_eq!(json!(1), game["id"]);
+        assert_eq!(json!(board::generate_new_board()), game["board"]);
+
+        let (status, game) = api.handle("POST", "/games/1/moves", r#"{"move": [0, 2]}"#);
+        assert_eq!(200, status);
+        assert_eq!(json!([null, null, "X"]), game["board"][0]);
+        assert_eq!(json!([[0, 2]]), game["moves"]);
+        assert_eq!(json!("O"), game["to_move"]);
+
+        let (status, _) = api.handle("POST", "/games/1/moves", r#"{"move": [0, 2]}"#);
+        assert_eq!(400, status);
+        for invalid in [r#"{"move": [0, 3]}"#, r#"{"x": 1, "y": 1}"#] {
+            assert_eq!(400, api.handle("POST", "/games/1/moves", invalid).0);
+        }
+        let (status, hint) = api.handle("POST", "/games/1/hint", r#"{"engine": "Rules"}"#);
+        assert_eq!(200, status);
+        assert_eq!(
+            json!({"coord": [1, 1], "score": null, "pv": [[1, 1]]}),
+            hint
+        );
+        let (status, game) = api.handle("POST", "/games/1/moves", r#"{"engine": "Rules"}"#);
+        assert_eq!(200, status);
+        assert_eq!(json!([null, "O", null]), game["board"][1]);
+        assert_eq!(game, api.handle("GET", "/games/1", "").1);
+        let (status, hint) = api.handle("POST", "/games/1/hint", r#"{"engine": "Level10"}"#);
+        assert_eq!(200, status);
+        assert_eq!(json!([0, 0]), hint["coord"]);
+        for invalid in [r#"{"engine": "Z"}"#, r#"{"engine": {"Level": 200}}"#] {
+            assert_eq!(400, api.handle("POST", "/games/1/hint", invalid).0);
+        }
+
+        assert_eq!(404, api.handle("GET", "/games/2", "").0);
+        assert_eq!(404, api.handle("DELETE", "/games/1", "").0);
+        assert_eq!(400, api.handle("POST", "/games/1/moves", "{").0);
+    }
+
+    #[test]
+    fn test_evaluate() {
+        init();
+        let mut api = Api::default();
+        let board = json!([["X", "X", null], ["O", "O", null], [null, null, null]]);
+        let body = json!({ "board": board }).to_string();
+        let (status, evaluation) = api.handle("POST", "/evaluate", &body);
+        assert_eq!(200, status);
+        assert_eq!(board, evaluation["board"]);
+        assert_eq!(json!("X"), evaluation["to_move"]);
+        assert_eq!(json!(14), evaluation["value"]);
+        let best = &evaluation["moves"][0];
+        assert_eq!(json!([0, 2]), best["move"]);
+        assert_eq!(json!("win in 1 move"), best["outcome"]);
+
+        for impossible in ["OOO/.../...", "XXX/OOO/...", "XX./.../..."] {
+            let board = board::parse_board(impossible).unwrap();
+            let body = json!({ "board": board }).to_string();
+            assert_eq!(400, api.handle("POST", "/evaluate", &body).0);
+        }
+        for invalid in [
+            json!([["Z", null, null], [null, null, null], [null, null, null]]),
+            json!([["x", null, null], [null, null, null], [null, null, null]]),
+            json!("XX./OO./..."),
+        ] {
+            let body = json!({ "board": invalid }).to_string();
+            assert_eq!(400, api.handle("POST", "/evaluate", &body).0);
+        }
+    }
+}
@@ -0,0 +1,96 @@
+//! Tic-tac-toe boards, rules, engines and game loop.
+//!
+//! A board is a `[[Option<char>; 3]; 3]` indexed by row then column, and a move is a
+//! `(row, column)` pair. Engines are values of [`Engine`], asked for a move with
+//! [`game::ask_engine`] or played against each other with [`play_game`] and [`play_match`].
+//!
+//! With the `serde` feature, [`Player`], [`Engine`], [`GameRecord`] and
+//! [`ai::EngineMove`] implement `Serialize` and `Deserialize`. Their JSON shape is given by
+//! their documentation and by the one of [`Board`] and [`Move`]. Boards and moves are
+//! validated by the helpers [`board::serde_board`], [`board::serde_move`] and
+//! [`board::serde_moves`]. The `http` and `spectate` features use these shapes and enable
+//! `serde`.
+//!
+//! The servers and the terminal interface are behind cargo features, all enabled by default :
+//! `net` (network games and the lobby server), `http` (HTTP/JSON API), `spectate` (WebSocket
+//! spectators) and `tui` (full screen terminal interface).
+//!
+//! ```
+//! use tictactoe::{board, game, Engine, EngineData, Player};
+//!
+//! let b = board::parse_board("XX./OO./...").unwrap();
+//! let data = EngineData::default();
+//! let best = game::ask_engine(&b, &Player::PlayerX, &Engine::Rules, &data).unwrap();
+//! assert_eq!((0, 2), best.coord);
+//!
+//! let record = game::play_game(
+//!     Engine::Rules,
+//!     Engine::Rules,
+//!     Default::default(),
+//!     &data,
+//!     &mut [],
+//! )
+//! .unwrap();
+//! assert_eq!(None, record.winner);
+//! ```
+
+#![warn(missing_docs)]
+
+/// Search engines : random, winning moves, minimax and levels, legal moves and evaluation
+pub mod ai;
+/// Move by move analysis of a finished game
+pub mod analysis;
+/// Board, moves and their text formats
+pub mod board;
+/// Time controls and chess clocks
+pub mod clock;
+/// Graphviz export of the game tree
+pub mod dot;
+/// Evolution of the heuristic weights
+pub mod evolution;
+/// Engines and the game loop
+pub mod game;
+/// Hash of a position, used as cache key by the searches
+mod hash;
+/// Engine scoring moves with weighted features
+pub mod heuristic;
+/// HTTP/JSON API
+#[cfg(feature = "http")]
+pub mod http;
+/// Server hosting concurrent network games
+#[cfg(feature = "net")]
+pub mod lobby;
+/// MENACE matchbox learning
+pub mod menace;
+/// Network games over TCP
+#[cfg(feature = "net")]
+pub mod net;
+/// Neural network engine
+pub mod nn;
+/// Observers of the game loop and output policy
+pub mod observer;
+/// Players and human input
+pub mod player;
+/// Q-learning engine
+pub mod qlearning;
+/// Board rendering themes
+pub mod render;
+/// Newell and Simon strategy rules
+mod rules;
+/// Full game tree solver
+pub mod solver;
+/// WebSocket spectators
+#[cfg(feature = "spectate")]
+pub mod spectate;
+/// Precomputed table of the best moves
+pub mod tablebase;
+/// Training of the learning engines
+pub mod training;
+/// Full screen terminal interface
+#[cfg(feature = "tui")]
+pub mod tui;
+
+pub use board::{Board, Move};
+pub use game::{play_game, play_match, Engine, EngineData, GameRecord};
+pub use observer::GameObserver;
+pub use player::Player;
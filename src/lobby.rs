@@ -0,0 +1,434 @@
+use std::collections::BTreeMap;
+use std::io::{self, BufReader, Cursor, ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use log::info;
+
+use crate::game::{self, Engine, EngineData};
+use crate::net::{self, Seat};
+use crate::observer::{Output, Verbosity};
+use crate::player::Player;
+
+/// Longest wait for the command of a client
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Players waiting for an opponent
+#[derive(Default)]
+struct Lobby {
+    next_id: u64,
+    /// games created and waiting for an opponent, with the connection of their creator
+    open: BTreeMap<u64, TcpStream>,
+    /// player waiting for matchmaking
+    queue: Option<TcpStream>,
+}
+
+/// Host many concurrent games on `address`, one thread per client. A client sends one line :
+/// - `LIST` : answered by `GAMES <id> <id> ...`, the games waiting for an opponent
+/// - `ENGINES` : answered by `ENGINES <name> <name> ...`, the engines available as opponents
+///   (the ones playing from data only when it is loaded)
+/// - `CREATE` : answered by `CREATED <id>`, the creator playing X once someone joins
+/// - `JOIN <id>` : play O in a created game
+/// - `PLAY` : play the next player doing the same, or wait for it after a `WAITING` answer
+/// - `ENGINE <name> [X|O]` : play the engine, with X by default
+///
+/// then the game follows the protocol of `net::host`. Errors are answered by `ERROR <reason>`.
+/// Clients are never written to while the lobby is locked, so that a slow client only slows
+/// its own game.
+///
+/// The address listened on is printed to `out`.
+pub fn serve(address: &str, data: EngineData, out: &mut impl Write) -> Result<()> {
+    let listener = TcpListener::bind(address)?;
+    writeln!(out, "Lobby listening on {}", listener.local_addr()?)?;
+    serve_on(listener, Arc::new(data));
+    Ok(())
+}
+
+fn serve_on(listener: TcpListener, data: Arc<EngineData>) {
+    let lobby = Arc::new(Mutex::new(Lobby::default()));
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                info!("Connection failed : {}", e);
+                continue;
+            }
+        };
+        let lobby = Arc::clone(&lobby);
+        let data = Arc::clone(&data);
+        thread::spawn(move || {
+            if let Err(e) = handle_client(stream, &lobby, &data) {
+                info!("Client error : {}", e);
+            }
+        });
+    }
+}
+
+/// Whether a client waiting for an opponent is still connected : it sends nothing while it
+/// waits, so the end of its stream or an error means that it left
+fn is_connected(stream: &TcpStream) -> bool {
+    if stream.set_nonblocking(true).is_err() {
+        return false;
+    }
+    let connected = match stream.peek(&mut [0]) {
+        Ok(0) => false,
+        Ok(_) => true,
+        Err(e) => e.kind() == ErrorKind::WouldBlock,
+    };
+    stream.set_nonblocking(false).is_ok() && connected
+}
+
+fn handle_client(stream: TcpStream, lobby: &Mutex<Lobby>, data: &EngineData) -> Result<()> {
+    stream.set_read_timeout(Some(COMMAND_TIMEOUT))?;
+    stream.set_write_timeout(Some(net::WRITE_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+    let silent = Output {
+        verbosity: Verbosity::Silent,
+        ..Default::default()
+    };
+    loop {
+        let line = net::receive(&mut reader)?;
+        let (command, argument) = line.split_once(' ').unwrap_or((&line, ""));
+        match command {
+            "LIST" => {
+                let mut lobby = lobby.lock().unwrap();
+                lobby.open.retain(|_, creator| is_connected(creator));
+                let ids: Vec<String> = lobby.open.keys().map(|id| id.to_string()).collect();
+                drop(lobby);
+                net::send(&mut writer, format!("GAMES {}", ids.join(" ")).trim_end())?;
+            }
+            "ENGINES" => {
+                let names: Vec<String> = game::engine_names()
+                    .into_iter()
+                    .filter(|name| name != "Human")
+                    .filter(|name| {
+                        game::engine_from_name(name).is_some_and(|e| data.has_data_for(&e))
+                    })
+                    .collect();
+                net::send(&mut writer, &format!("ENGINES {}", names.join(" ")))?;
+            }
+            "CREATE" => {
+                let id = {
+                    let mut lobby = lobby.lock().unwrap();
+                    lobby.next_id += 1;
+                    lobby.next_id
+                };
+                net::send(&mut writer, &format!("CREATED {}", id))?;
+                lobby.lock().unwrap().open.insert(id, writer);
+                info!("Game {} created", id);
+                return Ok(());
+            }
+            "JOIN" => {
+                let creator = match argument.parse() {
+                    Ok(id) => lobby.lock().unwrap().open.remove(&id),
+                    Err(_) => None,
+                }
+                .filter(is_connected);
+                match creator {
+                    Some(creator) => {
+                        info!("Game {} joined", argument);
+                        net::host(
+                            [Seat::Remote(creator), Seat::Remote(writer)],
+                            data,
+                            &silent,
+                            &mut io::sink(),
+                        )?;
+                        return Ok(());
+                    }
+                    None => net::send(&mut writer, &format!("ERROR no game {}", argument))?,
+                }
+            }
+            "PLAY" => {
+                let mut waiting = lobby.lock().unwrap().queue.take().filter(is_connected);
+                if waiting.is_none() {
+                    net::send(&mut writer, "WAITING")?;
+                    // another player may have queued while the answer was sent
+                    let mut locked = lobby.lock().unwrap();
+                    waiting = locked.queue.take().filter(is_connected);
+                    if waiting.is_none() {
+                        locked.queue = Some(writer);
+                        return Ok(());
+                    }
+                }
+                if let Some(waiting) = waiting {
+                    net::host(
+                        [Seat::Remote(waiting), Seat::Remote(writer)],
+                        data,
+                        &silent,
+                        &mut io::sink(),
+                    )?;
+                }
+                return Ok(());
+            }
+            "ENGINE" => {
+                let (name, side) = argument.split_once(' ').unwrap_or((argument, "X"));
+                let engine = match game::engine_from_name(name) {
+                    Some(Engine::Human) | None => {
+                        net::send(&mut writer, &format!("ERROR unknown engine {}", name))?;
+                        continue;
+                    }
+                    Some(engine) if !data.has_data_for(&engine) => {
+                        net::send(&mut writer, &format!("ERROR no data for {}", name))?;
+                        continue;
+                    }
+                    Some(engine) => engine,
+                };
+                let seats = match side {
+                    "X" => [Seat::Remote(writer), Seat::Local(engine)],
+                    "O" => [Seat::Local(engine), Seat::Remote(writer)],
+                    _ => {
+                        net::send(&mut writer, &format!("ERROR unknown side {}", side))?;
+                        continue;
+                    }
+                };
+                net::host(seats, data, &silent, &mut io::sink())?;
+                return Ok(());
+            }
+            _ => net::send(&mut writer, &format!("ERROR unknown command {}", command))?,
+        }
+    }
+}
+
+/// Send a `LIST` or `ENGINES` request and return the answer
+pub fn request(address: &str, command: &str) -> Result<String> {
+    let stream = TcpStream::connect(address)?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+    net::send(&mut writer, command)?;
+    net::receive(&mut reader)
+}
+
+/// Send a `CREATE`, `JOIN`, `PLAY` or `ENGINE` request and play the game, printing it to
+/// `out`. Return the winner.
+pub fn play(
+    address: &str,
+    command: &str,
+    engine: Engine,
+    data: &EngineData,
+    output: &Output,
+    out: &mut impl Write,
+) -> Result<Option<Player>> {
+    let stream = TcpStream::connect(address)?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+    net::send(&mut writer, command)?;
+    let first = loop {
+        let line = net::receive(&mut reader)?;
+        let (answer, argument) = line.split_once(' ').unwrap_or((&line, ""));
+        match answer {
+            "CREATED" => writeln!(out, "Game {} created, waiting for an opponent", argument)?,
+            "WAITING" => writeln!(out, "Waiting for an opponent")?,
+            "ERROR" => bail!("{}", argument),
+            _ => break line,
+        }
+    };
+    // the first message of the game is read again by the client
+    let mut reader = Cursor::new(first + "\n").chain(reader);
+    net::play_as_client(&mut reader, &mut writer, engine, data, output, out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    fn init() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    fn start_lobby() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || serve_on(listener, Arc::default()));
+        address
+    }
+
+    fn silent() -> Output {
+        Output {
+            verbosity: Verbosity::Silent,
+            ..Default::default()
+        }
+    }
+
+    /// Play in the background, the lobby answering `answer` to `LIST` once it is waiting
+    fn play_in_background(
+        address: &str,
+        command: &'static str,
+        answer: &str,
+    ) -> thread::JoinHandle<Option<Player>> {
+        let background_address = address.to_owned();
+        let client = thread::spawn(move || {
+            let data = EngineData::default();
+            play(
+                &background_address,
+                command,
+                Engine::Rules,
+                &data,
+                &silent(),
+                &mut io::sink(),
+            )
+            .unwrap()
+        });
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while request(address, "LIST").unwrap() != answer && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        client
+    }
+
+    #[test]
+    fn test_create_and_join() {
+        init();
+        let address = start_lobby();
+        assert_eq!("GAMES", request(&address, "LIST").unwrap());
+        let creator = play_in_background(&address, "CREATE", "GAMES 1");
+        assert_eq!("GAMES 1", request(&address, "LIST").unwrap());
+        let data = EngineData::default();
+        let error = play(
+            &address,
+            "JOIN 2",
+            Engine::Rules,
+            &data,
+            &silent(),
+            &mut io::sink(),
+        );
+        assert_eq!("no game 2", error.unwrap_err().to_string());
+        let winner = play(
+            &address,
+            "JOIN 1",
+            Engine::Rules,
+            &data,
+            &silent(),
+            &mut io::sink(),
+        )
+        .unwrap();
+        assert_eq!(None, winner);
+        assert_eq!(None, creator.join().unwrap());
+        assert_eq!("GAMES", request(&address, "LIST").unwrap());
+    }
+
+    #[test]
+    fn test_matchmaking_and_engines() {
+        init();
+        let address = start_lobby();
+        let engines = request(&address, "ENGINES").unwrap();
+        assert!(engines.starts_with("ENGINES RandomMove"));
+        assert!(engines.contains(" Level10"));
+        assert!(!engines.contains("Tablebase"));
+        assert_eq!(
+            "ERROR unknown side Z",
+            request(&address, "ENGINE Rules Z").unwrap()
+        );
+
+        let data = EngineData::default();
+        let first = thread::spawn({
+            let address = address.clone();
+            move || {
+                let data = EngineData::default();
+                play(
+                    &address,
+                    "PLAY",
+                    Engine::Rules,
+                    &data,
+                    &silent(),
+                    &mut io::sink(),
+                )
+                .unwrap()
+            }
+        });
+        let second = play(
+            &address,
+            "PLAY",
+            Engine::Rules,
+            &data,
+            &silent(),
+            &mut io::sink(),
+        )
+        .unwrap();
+        assert_eq!(None, second);
+        assert_eq!(None, first.join().unwrap());
+
+        let winner = play(
+            &address,
+            "ENGINE Rules O",
+            Engine::Rules,
+            &data,
+            &silent(),
+            &mut io::sink(),
+        )
+        .unwrap();
+        assert_eq!(None, winner);
+        let error = play(
+            &address,
+            "ENGINE Human",
+            Engine::Rules,
+            &data,
+            &silent(),
+            &mut io::sink(),
+        );
+        assert!(error.is_err());
+    }
+
+    #[test]
+    fn test_clients_leaving() {
+        init();
+        let address = start_lobby();
+        let leave_after = |command: &str, answer: &str| {
+            let stream = TcpStream::connect(&address).unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut writer = stream;
+            net::send(&mut writer, command).unwrap();
+            assert_eq!(answer, net::receive(&mut reader).unwrap());
+        };
+        leave_after("CREATE", "CREATED 1");
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while request(&address, "LIST").unwrap() != "GAMES" {
+            assert!(Instant::now() < deadline);
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        // the next players are matched together, not with the one who left
+        leave_after("PLAY", "WAITING");
+        let data = EngineData::default();
+        let first = thread::spawn({
+            let address = address.clone();
+            move || {
+                let data = EngineData::default();
+                play(
+                    &address,
+                    "PLAY",
+                    Engine::Rules,
+                    &data,
+                    &silent(),
+                    &mut io::sink(),
+                )
+                .unwrap()
+            }
+        });
+        let second = play(
+            &address,
+            "PLAY",
+            Engine::Rules,
+            &data,
+            &silent(),
+            &mut io::sink(),
+        )
+        .unwrap();
+        assert_eq!(None, second);
+        assert_eq!(None, first.join().unwrap());
+
+        let error = play(
+            &address,
+            "ENGINE Tablebase",
+            Engine::Rules,
+            &data,
+            &silent(),
+            &mut io::sink(),
+        );
+        assert_eq!("no data for Tablebase", error.unwrap_err().to_string());
+    }
+}
@@ -1,9 +1,15 @@
-mod ai;
-mod board;
-mod game;
-mod hash;
-mod player;
-use game::Engine;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use tictactoe::clock::TimeControl;
+use tictactoe::game::{Engine, EngineData};
+use tictactoe::observer::{self, ConsoleObserver, GameObserver, Output};
+use tictactoe::render::Theme;
+use tictactoe::{
+    ai, analysis, board, dot, evolution, game, heuristic, http, lobby, menace, net, nn, player,
+    qlearning, render, solver, spectate, tablebase, training, tui,
+};
 //TODO : how to add an AI like a plugin?
 //use anyhow::{Context,Result};
 use log::info;
@@ -11,62 +17,812 @@ use log::info;
 //pub type Error = anyhow::Error;
 //pub type Result<T> = anyhow::Result<T>;
 
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
     /// Engine for player X
-    #[arg(short, long)]
-    x: String,
+    #[arg(short, long, required = true)]
+    x: Option<String>,
     /// Engine for player Y
-    #[arg(short, long)]
-    o: String,
+    #[arg(short, long, required = true)]
+    o: Option<String>,
     /// Number of iteration
-    #[arg(short, long)]
-    i: usize,
+    #[arg(short, long, required = true)]
+    i: Option<usize>,
+    /// Time per game for each player, in seconds
+    #[arg(long, value_parser = parse_seconds)]
+    time: Option<Duration>,
+    /// Time added after each move, in seconds
+    #[arg(long, value_parser = parse_seconds, default_value = "0")]
+    increment: Duration,
+    /// Maximum time per move, in seconds
+    #[arg(long, value_parser = parse_seconds)]
+    move_time: Option<Duration>,
+    /// How MinMax chooses between moves of equal score
+    #[arg(long, value_enum, default_value_t = TieBreak::First)]
+    tie_break: TieBreak,
+    /// Annotate the mistakes of each game with the minimax solver
+    #[arg(long)]
+    analyze: bool,
+    #[command(flatten)]
+    files: DataFiles,
+    /// Board style
+    #[arg(long, value_enum, default_value_t = Style::Auto)]
+    style: Style,
+    /// What is printed
+    #[arg(long, value_enum, default_value_t = Verbosity::Boards)]
+    verbosity: Verbosity,
+    /// Only print the final score, like --verbosity summary
+    #[arg(short, long, conflicts_with = "verbosity")]
+    quiet: bool,
+    /// Stream the games to WebSocket spectators connected on this address
+    #[arg(long)]
+    spectate: Option<String>,
+    /// Play in a full-screen terminal interface (time controls are ignored)
+    #[arg(long, conflicts_with = "spectate")]
+    tui: bool,
+}
+
+/// Files of the engines playing from data
+#[derive(clap::Args, Debug)]
+struct DataFiles {
+    /// Tablebase file used by the Tablebase engine
+    #[arg(long)]
+    tablebase: Option<PathBuf>,
+    /// Q-table file used by the QLearning engine
+    #[arg(long)]
+    qtable: Option<PathBuf>,
+    /// Matchboxes file used by the Menace engine
+    #[arg(long)]
+    menace: Option<PathBuf>,
+    /// Network file used by the Neural engine
+    #[arg(long)]
+    network: Option<PathBuf>,
+    /// Weights file used by the Heuristic engine
+    #[arg(long)]
+    weights: Option<PathBuf>,
+}
+
+/// Labels of the samples the Neural engine is trained on
+#[derive(ValueEnum, Debug, Clone, Copy)]
+enum Labels {
+    /// Minimax best moves and value of every position
+    Minimax,
+    /// Results of games of the network against itself
+    SelfPlay,
+}
+
+/// How MinMax chooses between moves of equal score
+#[derive(ValueEnum, Debug, Clone, Copy)]
+enum TieBreak {
+    /// First move in (row, column) order
+    First,
+    /// Random move
+    Random,
+    /// Center, then corners, then sides
+    Preferred,
+}
+
+/// Board style
+#[derive(ValueEnum, Debug, Clone, Copy)]
+enum Style {
+    /// Plain characters
+    Ascii,
+    /// Box-drawing frame
+    Unicode,
+    /// Box-drawing frame and colors
+    Color,
+    /// Color in a terminal, ascii otherwise
+    Auto,
+}
+
+/// What is printed while playing
+#[derive(ValueEnum, Debug, Clone, Copy)]
+enum Verbosity {
+    /// Nothing
+    Silent,
+    /// The final score
+    Summary,
+    /// The result of each game
+    Results,
+    /// Every board
+    Boards,
+}
+
+/// Side of a network game
+#[derive(ValueEnum, Debug, Clone, Copy)]
+enum Side {
+    #[value(name = "X")]
+    X,
+    #[value(name = "O")]
+    O,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Enumerate and solve every reachable position
+    Solve {
+        /// Print every unique position with its value
+        #[arg(long)]
+        list: bool,
+    },
+    /// Export the game tree as a Graphviz DOT graph on the standard output
+    Dot {
+        /// Root position, "X", "O" or "." for each case, like "X.O/.X./O.."
+        #[arg(long, default_value = ".../.../...")]
+        position: String,
+        /// Maximum number of moves from the root position
+        #[arg(long)]
+        depth: Option<usize>,
+        /// Merge the positions equal by symmetry
+        #[arg(long)]
+        symmetry: bool,
+    },
+    /// Generate a tablebase file of best moves
+    Tablebase {
+        /// Output file
+        #[arg(long)]
+        output: PathBuf,
+        /// Only include the positions with at most this number of moves played
+        #[arg(long)]
+        max_depth: Option<usize>,
+    },
+    /// Train the QLearning engine and save its Q-table
+    TrainQ {
+        /// Output file
+        #[arg(long)]
+        output: PathBuf,
+        /// Q-table to continue training from
+        #[arg(long)]
+        input: Option<PathBuf>,
+        /// Number of training games
+        #[arg(long, default_value_t = 10000)]
+        games: usize,
+        /// Engine to train against, "Self" for self-play
+        #[arg(long, default_value = "Self")]
+        opponent: String,
+        /// Share of the new estimate in each update of a Q-value, between 0 and 1
+        #[arg(long, default_value_t = 0.5, value_parser = parse_probability)]
+        learning_rate: f64,
+        /// Weight of the next position's value, between 0 and 1
+        #[arg(long, default_value_t = 0.9, value_parser = parse_probability)]
+        discount: f64,
+        /// Probability to explore a random move
+        #[arg(long, default_value_t = 0.1, value_parser = parse_probability)]
+        epsilon: f64,
+        /// Engine playing the evaluation games printed during training
+        #[arg(long, default_value = "MinMax")]
+        eval_opponent: String,
+        /// Number of evaluation games
+        #[arg(long, default_value_t = 100)]
+        eval_games: usize,
+        /// Number of training games between evaluations
+        #[arg(long, default_value_t = 1000, value_parser = clap::value_parser!(u64).range(1..))]
+        eval_every: u64,
+    },
+    /// Train the Menace engine and save its matchboxes
+    TrainMenace {
+        /// Output file
+        #[arg(long)]
+        output: PathBuf,
+        /// Matchboxes to continue training from
+        #[arg(long)]
+        input: Option<PathBuf>,
+        /// Number of training games
+        #[arg(long, default_value_t = 1000)]
+        games: usize,
+        /// Engine to train against, "Self" for self-play
+        #[arg(long, default_value = "WinningAndNotLosingMove")]
+        opponent: String,
+        /// Engine playing the evaluation games printed during training
+        #[arg(long, default_value = "MinMax")]
+        eval_opponent: String,
+        /// Number of evaluation games
+        #[arg(long, default_value_t = 100)]
+        eval_games: usize,
+        /// Number of training games between evaluations
+        #[arg(long, default_value_t = 100, value_parser = clap::value_parser!(u64).range(1..))]
+        eval_every: u64,
+    },
+    /// Train the Neural engine and save its network
+    TrainNn {
+        /// Output file
+        #[arg(long)]
+        output: PathBuf,
+        /// Network to continue training from
+        #[arg(long)]
+        input: Option<PathBuf>,
+        /// Training labels
+        #[arg(long, value_enum, default_value_t = Labels::Minimax)]
+        labels: Labels,
+        /// Size of the hidden layer of a new network
+        #[arg(long, default_value_t = 32, value_parser = clap::value_parser!(u64).range(1..))]
+        hidden: u64,
+        #[arg(long, default_value_t = 100)]
+        epochs: usize,
+        /// Step of the gradient descent, above 0 and at most 1
+        #[arg(long, default_value_t = 0.02, value_parser = parse_learning_rate)]
+        learning_rate: f64,
+        /// Number of self-play games per epoch
+        #[arg(long, default_value_t = 200)]
+        games: usize,
+    },
+    /// Host a game over TCP and wait for an opponent
+    Serve {
+        /// Address to listen on
+        #[arg(long, default_value = "0.0.0.0:7878")]
+        address: String,
+        /// Engine playing on this side
+        #[arg(long, default_value = "Human")]
+        engine: String,
+        /// Side played on this side
+        #[arg(long, value_enum, ignore_case = true, default_value_t = Side::X)]
+        side: Side,
+        #[command(flatten)]
+        files: DataFiles,
+    },
+    /// Join a game hosted over TCP
+    Connect {
+        /// Address of the server
+        #[arg(long, default_value = "127.0.0.1:7878")]
+        address: String,
+        /// Engine playing on this side
+        #[arg(long, default_value = "Human")]
+        engine: String,
+        #[command(flatten)]
+        files: DataFiles,
+    },
+    /// Host many concurrent games, with matchmaking and engine opponents
+    LobbyServer {
+        /// Address to listen on
+        #[arg(long, default_value = "0.0.0.0:7879")]
+        address: String,
+        #[command(flatten)]
+        files: DataFiles,
+    },
+    /// Play through a lobby server : matchmaking by default
+    Lobby {
+        /// Address of the lobby server
+        #[arg(long, default_value = "127.0.0.1:7879")]
+        address: String,
+        /// Engine playing on this side
+        #[arg(long, default_value = "Human")]
+        engine: String,
+        /// List the games waiting for an opponent
+        #[arg(long)]
+        list: bool,
+        /// List the engines available as opponents
+        #[arg(long)]
+        engines: bool,
+        /// Create a game and wait for an opponent
+        #[arg(long)]
+        create: bool,
+        /// Join a created game
+        #[arg(long)]
+        join: Option<u64>,
+        /// Play against an engine of the server
+        #[arg(long)]
+        opponent: Option<String>,
+        /// Side played against an engine
+        #[arg(long, value_enum, ignore_case = true, default_value_t = Side::X)]
+        side: Side,
+        #[command(flatten)]
+        files: DataFiles,
+    },
+    /// Serve an HTTP/JSON API to play and analyze games
+    Api {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        address: String,
+        #[command(flatten)]
+        files: DataFiles,
+    },
+    /// Tune the weights of the Heuristic engine with a genetic algorithm
+    Evolve {
+        /// Output file
+        #[arg(long)]
+        output: PathBuf,
+        /// Weights to start from, the default ones otherwise
+        #[arg(long)]
+        input: Option<PathBuf>,
+        /// Number of weight vectors in each generation
+        #[arg(long, default_value_t = 20, value_parser = clap::value_parser!(u64).range(2..))]
+        population: u64,
+        #[arg(long, default_value_t = 20)]
+        generations: usize,
+        /// Standard deviation of the noise added to the weights, at least 0
+        #[arg(long, default_value_t = 1.0, value_parser = parse_mutation)]
+        mutation: f64,
+        /// Number of games with each side against each opponent
+        #[arg(long, default_value_t = 10)]
+        games: usize,
+        /// Comma separated engines the fitness is measured against
+        #[arg(long, default_value = "RandomMove,WinningAndNotLosingMove,MinMax")]
+        opponents: String,
+    },
 }
 
 fn main() {
     env_logger::init();
     info!("Launching tictactoe");
     let args = Args::parse();
-    let engine_x: Engine = get_engine_from_arg(&args.x);
-    let engine_o: Engine = get_engine_from_arg(&args.o);
-    player::print_player_input_rule();
-
-    let iteration_number = args.i;
-    let mut results: (usize, usize, usize) = (0, 0, 0);
-
-    for _i in 0..iteration_number {
-        match game::play_game(engine_x, engine_o) {
-            Some(p) => {
-                println!("Well done {:?} !!!", p);
-                match p {
-                    player::Player::PlayerX => results.0 += 1,
-                    player::Player::PlayerO => results.1 += 1,
+    match args.command {
+        Some(Command::Solve { list }) => {
+            let mut out = io::stdout();
+            let printed = if list {
+                solver::print_unique_positions(&mut out)
+            } else {
+                Ok(())
+            };
+            if let Err(e) = printed.and_then(|()| solver::print_report(&mut out, &solver::solve()))
+            {
+                println!("Error : {}", e);
+            }
+        }
+        Some(Command::Dot {
+            position,
+            depth,
+            symmetry,
+        }) => match board::parse_board(&position)
+            .and_then(|b| dot::game_tree_to_dot(&b, depth, symmetry))
+        {
+            Ok(dot) => print!("{}", dot),
+            Err(e) => println!("Error : {}", e),
+        },
+        Some(Command::Tablebase { output, max_depth }) => {
+            let tablebase = tablebase::Tablebase::generate(max_depth);
+            match tablebase.save(&output) {
+                Ok(()) => println!("{} positions saved", tablebase.len()),
+                Err(e) => println!("Error : {}", e),
+            }
+        }
+        Some(Command::TrainQ {
+            output,
+            input,
+            games,
+            opponent,
+            learning_rate,
+            discount,
+            epsilon,
+            eval_opponent,
+            eval_games,
+            eval_every,
+        }) => {
+            let params = qlearning::QLearningParams {
+                learning_rate,
+                discount,
+                epsilon,
+            };
+            let schedule = training::Schedule {
+                games,
+                opponent: get_opponent_from_arg(&opponent),
+                eval_opponent: get_engine_from_arg(&eval_opponent, ai::TieBreak::First),
+                eval_games,
+                eval_every: eval_every as usize,
+            };
+            let new = qlearning::QTable::default;
+            let saved = training::train_and_save(input.as_deref(), &output, new, |table| {
+                let on_evaluation = print_evaluation(schedule.eval_opponent);
+                qlearning::train_qtable(table, &schedule, &params, on_evaluation)
+            });
+            print_saved(saved);
+        }
+        Some(Command::TrainMenace {
+            output,
+            input,
+            games,
+            opponent,
+            eval_opponent,
+            eval_games,
+            eval_every,
+        }) => {
+            let schedule = training::Schedule {
+                games,
+                opponent: get_opponent_from_arg(&opponent),
+                eval_opponent: get_engine_from_arg(&eval_opponent, ai::TieBreak::First),
+                eval_games,
+                eval_every: eval_every as usize,
+            };
+            let new = menace::Menace::default;
+            let saved = training::train_and_save(input.as_deref(), &output, new, |menace| {
+                let on_evaluation = print_evaluation(schedule.eval_opponent);
+                menace::train_menace(menace, &schedule, on_evaluation)
+            });
+            print_saved(saved);
+        }
+        Some(Command::TrainNn {
+            output,
+            input,
+            labels,
+            hidden,
+            epochs,
+            learning_rate,
+            games,
+        }) => {
+            let params = nn::NetworkTrainingParams {
+                labels: match labels {
+                    Labels::Minimax => nn::Labels::Minimax,
+                    Labels::SelfPlay => nn::Labels::SelfPlay,
+                },
+                epochs,
+                learning_rate,
+                games,
+            };
+            let mut rng = rand::thread_rng();
+            let new = || nn::Network::new(hidden as usize, &mut rand::thread_rng());
+            let saved = training::train_and_save(input.as_deref(), &output, new, |network| {
+                nn::train_network(network, &params, &mut rng, |epoch, loss, accuracy| {
+                    println!(
+                        "epoch {} : loss {:.4}, minimax best move accuracy {:.1}%",
+                        epoch,
+                        loss,
+                        100.0 * accuracy
+                    )
+                })
+            });
+            print_saved(saved);
+        }
+        Some(Command::Evolve {
+            output,
+            input,
+            population,
+            generations,
+            mutation,
+            games,
+            opponents,
+        }) => {
+            let params = evolution::EvolutionParams {
+                population: population as usize,
+                generations,
+                mutation,
+                ..Default::default()
+            };
+            let opponents: Vec<Engine> = opponents
+                .split(',')
+                .map(|name| get_engine_from_arg(name.trim(), ai::TieBreak::First))
+                .collect();
+            let new = heuristic::HeuristicWeights::default;
+            let saved = training::train_and_save(input.as_deref(), &output, new, |weights| {
+                *weights = evolution::evolve_against(
+                    weights,
+                    &params,
+                    &opponents,
+                    games,
+                    |generation, _, fitness| {
+                        println!("generation {} : best fitness {:.3}", generation, fitness)
+                    },
+                    &mut rand::thread_rng(),
+                )?;
+                for (name, weight) in heuristic::FEATURE_NAMES.iter().zip(weights.0.iter()) {
+                    println!("{} : {:.3}", name, weight);
                 }
+                Ok(())
+            });
+            print_saved(saved);
+        }
+        Some(Command::Serve {
+            address,
+            engine,
+            side,
+            files,
+        }) => {
+            let engine = get_engine_from_arg(&engine, ai::TieBreak::First);
+            let side = match side {
+                Side::X => player::Player::PlayerX,
+                Side::O => player::Player::PlayerO,
+            };
+            if engine == Engine::Human {
+                println!("{}", player::player_input_rule());
+            }
+            let output = Output {
+                theme: render::auto_theme(),
+                ..Default::default()
+            };
+            let (data, _) = load_engine_data(&files, false);
+            if let Err(e) = net::serve(&address, engine, side, &data, &output, &mut io::stdout()) {
+                println!("Error : {}", e);
+            }
+        }
+        Some(Command::Connect {
+            address,
+            engine,
+            files,
+        }) => {
+            let engine = get_engine_from_arg(&engine, ai::TieBreak::First);
+            if engine == Engine::Human {
+                println!("{}", player::player_input_rule());
+            }
+            let output = Output {
+                theme: render::auto_theme(),
+                ..Default::default()
+            };
+            let (data, _) = load_engine_data(&files, false);
+            if let Err(e) = net::connect(&address, engine, &data, &output, &mut io::stdout()) {
+                println!("Error : {}", e);
+            }
+        }
+        Some(Command::LobbyServer { address, files }) => {
+            let (data, _) = load_engine_data(&files, false);
+            if let Err(e) = lobby::serve(&address, data, &mut io::stdout()) {
+                println!("Error : {}", e);
+            }
+        }
+        Some(Command::Lobby {
+            address,
+            engine,
+            list,
+            engines,
+            create,
+            join,
+            opponent,
+            side,
+            files,
+        }) => {
+            if list || engines {
+                let command = if list { "LIST" } else { "ENGINES" };
+                match lobby::request(&address, command) {
+                    Ok(answer) => println!("{}", answer),
+                    Err(e) => println!("Error : {}", e),
+                }
+                return;
             }
-            None => {
-                println!("This is a draw !");
-                results.2 += 1
+            let command = match (create, join, opponent) {
+                (true, _, _) => "CREATE".to_owned(),
+                (_, Some(id), _) => format!("JOIN {}", id),
+                (_, _, Some(name)) => format!("ENGINE {} {:?}", name, side),
+                _ => "PLAY".to_owned(),
+            };
+            let engine = get_engine_from_arg(&engine, ai::TieBreak::First);
+            if engine == Engine::Human {
+                println!("{}", player::player_input_rule());
+            }
+            let output = Output {
+                theme: render::auto_theme(),
+                ..Default::default()
+            };
+            let (data, _) = load_engine_data(&files, false);
+            if let Err(e) = lobby::play(
+                &address,
+                &command,
+                engine,
+                &data,
+                &output,
+                &mut io::stdout(),
+            ) {
+                println!("Error : {}", e);
+            }
+        }
+        Some(Command::Api { address, files }) => {
+            let (data, _) = load_engine_data(&files, false);
+            if let Err(e) = http::serve(&address, data, &mut io::stdout()) {
+                println!("Error : {}", e);
             }
         }
+        None => play_games(&args),
     }
-    println!(
-        "X win {} ; O win {} ; draw {}.",
-        results.0, results.1, results.2
-    );
 }
 
-fn get_engine_from_arg(arg: &str) -> Engine {
+/// Load the data files of the engines, playing without the ones that fail to load. When
+/// MENACE learns, a missing matchboxes file is created and its path is returned.
+fn load_engine_data(files: &DataFiles, menace_learns: bool) -> (EngineData, Option<PathBuf>) {
+    let mut data = EngineData::default();
+    if let Some(path) = &files.tablebase {
+        match tablebase::Tablebase::load(path) {
+            Ok(t) => data.tablebase = Some(t),
+            Err(e) => println!("Error loading tablebase : {} ; searching instead.", e),
+        }
+    }
+    if let Some(path) = &files.qtable {
+        match qlearning::QTable::load(path) {
+            Ok(t) => data.qtable = Some(t),
+            Err(e) => println!("Error loading Q-table : {} ; playing randomly.", e),
+        }
+    }
+    let mut menace_path = None;
+    if let Some(path) = &files.menace {
+        match menace::Menace::load(path) {
+            Ok(m) => {
+                data.menace = Some(Mutex::new(m));
+                menace_path = Some(path.clone());
+            }
+            Err(_) if menace_learns && !path.exists() => {
+                println!("New matchboxes saved to {}", path.display());
+                menace_path = Some(path.clone());
+            }
+            Err(e) => println!("Error loading matchboxes : {} ; playing randomly.", e),
+        }
+    }
+    if menace_learns && data.menace.is_none() {
+        data.menace = Some(Mutex::default());
+    }
+    if let Some(path) = &files.network {
+        match nn::Network::load(path) {
+            Ok(n) => data.network = Some(n),
+            Err(e) => println!("Error loading network : {} ; playing randomly.", e),
+        }
+    }
+    if let Some(path) = &files.weights {
+        match heuristic::HeuristicWeights::load(path) {
+            Ok(w) => data.weights = Some(w),
+            Err(e) => println!("Error loading weights : {} ; default weights used.", e),
+        }
+    }
+    (data, menace_path)
+}
+
+fn play_games(args: &Args) {
+    let tie_break = match args.tie_break {
+        TieBreak::First => ai::TieBreak::First,
+        TieBreak::Random => ai::TieBreak::Random,
+        TieBreak::Preferred => ai::TieBreak::Preferred,
+    };
+    let engine_x: Engine = get_engine_from_arg(args.x.as_deref().unwrap(), tie_break);
+    let engine_o: Engine = get_engine_from_arg(args.o.as_deref().unwrap(), tie_break);
+    let time_control = TimeControl {
+        base: args.time,
+        increment: args.increment,
+        per_move: args.move_time,
+    };
+    // MENACE learns from the games of the match, saved to its file after each game
+    let menace_plays = engine_x == Engine::Menace || engine_o == Engine::Menace;
+    let (data, menace_path) = load_engine_data(&args.files, menace_plays);
+    let output = Output {
+        verbosity: match args.verbosity {
+            _ if args.quiet => observer::Verbosity::Summary,
+            Verbosity::Silent => observer::Verbosity::Silent,
+            Verbosity::Summary => observer::Verbosity::Summary,
+            Verbosity::Results => observer::Verbosity::Results,
+            Verbosity::Boards => observer::Verbosity::Boards,
+        },
+        theme: match args.style {
+            Style::Ascii => Theme::Ascii,
+            Style::Unicode => Theme::Unicode,
+            Style::Color => Theme::Color,
+            Style::Auto => render::auto_theme(),
+        },
+    };
+    let human_plays = engine_x == Engine::Human || engine_o == Engine::Human;
+    if human_plays && !args.tui {
+        println!("{}", player::player_input_rule());
+    }
+    // the terminal interface shows the boards and the results itself, and the analysis is
+    // printed once it is closed
+    let mut console = ConsoleObserver {
+        output,
+        out: io::stdout(),
+    };
+    let mut analysis = analysis::AnalysisObserver { out: io::stdout() };
+    let mut tui_analysis = analysis::AnalysisObserver { out: Vec::new() };
+    let mut observers: Vec<&mut dyn GameObserver> = Vec::new();
+    if !args.tui {
+        observers.push(&mut console);
+    }
+    let mut menace_observer;
+    if let (true, Some(menace)) = (menace_plays, &data.menace) {
+        menace_observer = menace::MenaceObserver::new(menace, menace_path);
+        observers.push(&mut menace_observer);
+    }
+    if args.analyze && output.shows(observer::Verbosity::Results) {
+        if args.tui {
+            observers.push(&mut tui_analysis);
+        } else {
+            observers.push(&mut analysis);
+        }
+    }
+    let mut broadcaster;
+    if let Some(address) = &args.spectate {
+        match spectate::Broadcaster::bind(address) {
+            Ok(b) => {
+                broadcaster = b;
+                println!("Spectators can watch on ws://{}", broadcaster.local_addr());
+                observers.push(&mut broadcaster);
+            }
+            Err(e) => println!("Error starting the spectator server : {}", e),
+        }
+    }
+    let played = if args.tui {
+        tui::play_games(
+            engine_x,
+            engine_o,
+            args.i.unwrap(),
+            time_control,
+            &data,
+            &mut observers,
+        )
+    } else {
+        game::play_match(
+            engine_x,
+            engine_o,
+            args.i.unwrap(),
+            time_control,
+            &data,
+            &mut observers,
+        )
+    };
+    drop(observers);
+    print!("{}", String::from_utf8_lossy(&tui_analysis.out));
+    let results = match played {
+        Ok(results) => results,
+        Err(e) => {
+            println!("Error : {}", e);
+            return;
+        }
+    };
+    if output.shows(observer::Verbosity::Summary) {
+        println!(
+            "X win {} ; O win {} ; draw {}.",
+            results.0, results.1, results.2
+        );
+    }
+}
+
+/// Learning rate of the network, above 0 and at most 1
+fn parse_learning_rate(arg: &str) -> Result<f64, String> {
+    let rate: f64 = arg.parse().map_err(|e| format!("{}", e))?;
+    if !(rate > 0.0 && rate <= 1.0) {
+        return Err("a learning rate is above 0 and at most 1".to_owned());
+    }
+    Ok(rate)
+}
+
+/// Standard deviation of the mutations, finite and at least 0
+fn parse_mutation(arg: &str) -> Result<f64, String> {
+    let sigma: f64 = arg.parse().map_err(|e| format!("{}", e))?;
+    if !(sigma.is_finite() && sigma >= 0.0) {
+        return Err("a mutation is a finite standard deviation, at least 0".to_owned());
+    }
+    Ok(sigma)
+}
+
+/// Probability between 0 and 1
+fn parse_probability(arg: &str) -> Result<f64, String> {
+    let p: f64 = arg.parse().map_err(|e| format!("{}", e))?;
+    if !(0.0..=1.0).contains(&p) {
+        return Err("a probability is between 0 and 1".to_owned());
+    }
+    Ok(p)
+}
+
+/// Duration of a positive number of seconds
+fn parse_seconds(arg: &str) -> Result<Duration, String> {
+    let seconds: f64 = arg.parse().map_err(|e| format!("{}", e))?;
+    Duration::try_from_secs_f64(seconds).map_err(|e| format!("{}", e))
+}
+
+/// Training opponent : None for "Self", the learning engine playing itself
+fn get_opponent_from_arg(arg: &str) -> Option<Engine> {
     match arg {
-        "Human" => Engine::Human,
-        "RandomMove" => Engine::RandomMove,
-        "WinningMove" => Engine::WinningMove,
-        "WinningAndNotLosingMove" => Engine::WinningAndNotLosingMove,
-        "MinMax" => Engine::MinMax,
-        _ => {
+        "Self" => None,
+        name => Some(get_engine_from_arg(name, ai::TieBreak::First)),
+    }
+}
+
+fn print_evaluation(opponent: Engine) -> impl FnMut(usize, &training::TrainingResults) {
+    move |games, r| {
+        println!(
+            "{} games : {} wins, {} draws, {} losses against {:?}",
+            games, r.wins, r.draws, r.losses, opponent
+        )
+    }
+}
+
+fn print_saved(saved: anyhow::Result<String>) {
+    match saved {
+        Ok(summary) => println!("{} saved", summary),
+        Err(e) => println!("Error : {}", e),
+    }
+}
+
+fn get_engine_from_arg(arg: &str, tie_break: ai::TieBreak) -> Engine {
+    match game::engine_from_name(arg) {
+        Some(Engine::MinMax(_)) => Engine::MinMax(tie_break),
+        Some(engine) => engine,
+        None => {
             println!("Unknown engine passed by args ; RandomMove selected.");
             Engine::RandomMove
         }
@@ -0,0 +1,376 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::{anyhow, bail, Result};
+use log::error;
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::Rng;
+
+use crate::ai::{self, EngineMove};
+use crate::board::{self, Board, Move};
+use crate::game::{self, Engine, EngineData, GameRecord};
+use crate::hash;
+use crate::observer::{EndReason, GameObserver, MoveEvent};
+use crate::player::Player;
+use crate::training::{self, Model, Schedule, TrainingResults};
+
+/// Beads added to each move played by MENACE after a win, a draw and a loss
+const REWARDS: (i32, i32, i32) = (3, 1, -1);
+
+/// One matchbox : a canonical position and the beads of each case (index y*3+x)
+#[derive(Debug, PartialEq, Clone)]
+struct Matchbox {
+    board: Board,
+    beads: [u32; 9],
+}
+
+impl Matchbox {
+    /// Michie's initial beads : 4 per legal move for the first moves of the game, then 3,
+    /// 2 and 1 for the last ones
+    fn new(board: &Board) -> Matchbox {
+        let moves_played = 9 - ai::find_all_legal_moves(board).len();
+        let initial = match moves_played {
+            0 | 1 => 4,
+            2 | 3 => 3,
+            4 | 5 => 2,
+            _ => 1,
+        };
+        let mut beads = [0; 9];
+        for m in ai::find_all_legal_moves(board) {
+            beads[m.0 * 3 + m.1] = initial;
+        }
+        Matchbox {
+            board: *board,
+            beads,
+        }
+    }
+}
+
+/// Donald Michie's Machine Educable Noughts And Crosses Engine : one matchbox per
+/// canonical position, a move being drawn with a probability proportional to its beads
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Menace {
+    matchboxes: HashMap<u64, Matchbox>,
+}
+
+impl Menace {
+    /// Number of matchboxes
+    pub fn len(&self) -> usize {
+        self.matchboxes.len()
+    }
+
+    /// Whether no matchbox was created yet
+    pub fn is_empty(&self) -> bool {
+        self.matchboxes.is_empty()
+    }
+
+    fn key(canonical: &Board) -> u64 {
+        hash::compute_cache(canonical, &board::player_to_move(canonical))
+    }
+
+    /// Draw a bead from the matchbox of the position. Return the move, and the move in the
+    /// canonical position. An empty matchbox means MENACE resigns : it plays randomly.
+    fn draw_bead(&self, board: &Board) -> Option<(Move, Move)> {
+        let (canonical, symmetry) = board::canonical_board(board);
+        let matchbox = match self.matchboxes.get(&Menace::key(&canonical)) {
+            Some(m) => m.clone(),
+            None => Matchbox::new(&canonical),
+        };
+        let legal_moves = ai::find_all_legal_moves(&canonical);
+        if legal_moves.is_empty() {
+            return None;
+        }
+        let mut rng = rand::thread_rng();
+        let weights = legal_moves.iter().map(|m| matchbox.beads[m.0 * 3 + m.1]);
+        let canonical_move = match WeightedIndex::new(weights) {
+            Ok(index) => legal_moves[index.sample(&mut rng)],
+            Err(_) => legal_moves[rng.gen_range(0..legal_moves.len())],
+        };
+        let m = board::transform_move(canonical_move, board::inverse_symmetry(symmetry));
+        Some((m, canonical_move))
+    }
+
+    /// Add `beads` (or remove them if negative) to a move of a canonical position
+    fn reinforce(&mut self, canonical: &Board, m: Move, beads: i32) {
+        let matchbox = self
+            .matchboxes
+            .entry(Menace::key(canonical))
+            .or_insert_with(|| Matchbox::new(canonical));
+        let count = &mut matchbox.beads[m.0 * 3 + m.1];
+        *count = (*count as i32 + beads).max(0) as u32;
+    }
+
+    /// Reinforce the moves played by MENACE in a game won by `winner` : `played` holds the
+    /// canonical positions and moves, with the player who moved
+    fn learn(&mut self, played: &[(Board, Move, Player)], winner: Option<Player>) {
+        for (b, m, p) in played {
+            let beads = match winner {
+                Some(w) if w == *p => REWARDS.0,
+                Some(_) => REWARDS.2,
+                None => REWARDS.1,
+            };
+            self.reinforce(b, *m, beads);
+        }
+    }
+
+    /// Text format : one matchbox per line, the board as read by `board::parse_board`
+    /// followed by the 9 bead counts
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let mut matchboxes: Vec<&Matchbox> = self.matchboxes.values().collect();
+        matchboxes.sort_by_key(|m| m.board);
+        let mut output = String::new();
+        for m in matchboxes {
+            let beads: Vec<String> = m.beads.iter().map(|b| b.to_string()).collect();
+            output += &format!("{} {}\n", board::board_to_string(&m.board), beads.join(" "));
+        }
+        fs::write(path, output)?;
+        Ok(())
+    }
+
+    /// Read matchboxes written by `save`
+    pub fn load(path: &Path) -> Result<Menace> {
+        let mut matchboxes = HashMap::new();
+        for line in fs::read_to_string(path)?.lines() {
+            let mut fields = line.split_whitespace();
+            let b = board::parse_board(fields.next().unwrap_or_default())?;
+            let beads: Vec<u32> = fields.map(|f| f.parse()).collect::<Result<_, _>>()?;
+            if beads.len() != 9 {
+                bail!("9 bead counts expected for {}", board::board_to_string(&b));
+            }
+            let matchbox = Matchbox {
+                board: b,
+                beads: beads.try_into().unwrap(),
+            };
+            matchboxes.insert(Menace::key(&b), matchbox);
+        }
+        Ok(Menace { matchboxes })
+    }
+}
+
+/// Play a game where MENACE plays `agent` against `opponent`, or both sides when
+/// `opponent` is None, and reinforce its matchboxes after the game if `learn` is set.
+/// Return the winner.
+pub fn play_menace_game(
+    menace: &mut Menace,
+    agent: Player,
+    opponent: Option<Engine>,
+    learn: bool,
+    data: &EngineData,
+) -> Result<Option<Player>> {
+    let mut board = board::generate_new_board();
+    let mut player = Player::PlayerX;
+    // canonical positions and moves played by MENACE, with the player
+    let mut played: Vec<(Board, Move, Player)> = Vec::new();
+
+    loop {
+        let m = match opponent {
+            Some(engine) if player != agent => {
+                game::ask_engine(&board, &player, &engine, data)?.coord
+            }
+            _ => {
+                let (m, canonical_move) = menace
+                    .draw_bead(&board)
+                    .ok_or_else(|| anyhow!("no legal move available"))?;
+                played.push((board::canonical_board(&board).0, canonical_move, player));
+                m
+            }
+        };
+        board = board::make_move(&board, m, &player)?;
+
+        let winner = board::is_move_win(&board);
+        if winner.is_some() || board::is_board_full(&board) {
+            if learn {
+                menace.learn(&played, winner);
+            }
+            return Ok(winner);
+        }
+        player = game::switch_player(&player);
+    }
+}
+
+impl Model for Menace {
+    fn load(path: &Path) -> Result<Menace> {
+        Menace::load(path)
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        Menace::save(self, path)
+    }
+
+    fn summary(&self) -> String {
+        format!("{} matchboxes", self.len())
+    }
+}
+
+/// Reinforce the matchboxes after each game where the Menace engine played, and save them
+/// to `path` if given
+pub struct MenaceObserver<'a> {
+    /// matchboxes reinforced
+    pub menace: &'a Mutex<Menace>,
+    /// file the matchboxes are saved to
+    pub path: Option<PathBuf>,
+    /// canonical positions and moves played by MENACE during the game, with the player
+    played: Vec<(Board, Move, Player)>,
+}
+
+impl<'a> MenaceObserver<'a> {
+    /// Observer of the games of `menace`, saved to `path` if given
+    pub fn new(menace: &'a Mutex<Menace>, path: Option<PathBuf>) -> MenaceObserver<'a> {
+        MenaceObserver {
+            menace,
+            path,
+            played: Vec::new(),
+        }
+    }
+}
+
+impl GameObserver for MenaceObserver<'_> {
+    fn on_game_started(&mut self, _engine_x: Engine, _engine_o: Engine, _board: &Board) {
+        self.played.clear();
+    }
+
+    fn on_move_played(&mut self, event: &MoveEvent) {
+        if event.engine != Engine::Menace {
+            return;
+        }
+        let m = event.engine_move.coord;
+        let mut before = event.board;
+        before[m.0][m.1] = None;
+        let (canonical, symmetry) = board::canonical_board(&before);
+        let canonical_move = board::transform_move(m, symmetry);
+        self.played.push((canonical, canonical_move, event.player));
+    }
+
+    fn on_game_ended(&mut self, record: &GameRecord, _reason: EndReason) {
+        if self.played.is_empty() {
+            return;
+        }
+        let mut menace = self.menace.lock().unwrap();
+        menace.learn(&self.played, record.winner);
+        if let Some(path) = &self.path {
+            if let Err(e) = menace.save(path) {
+                error!("Error saving the matchboxes : {}", e);
+            }
+        }
+    }
+}
+
+/// Draw a bead from the matchbox of the position, a fresh matchbox if MENACE never saw it
+pub fn menace_ai(board: &Board, menace: Option<&Menace>) -> Result<EngineMove> {
+    let m = match menace {
+        Some(menace) => menace.draw_bead(board),
+        None => Menace::default().draw_bead(board),
+    };
+    match m {
+        Some((m, _)) => Ok(m.into()),
+        None => Err(anyhow!("no legal move available")),
+    }
+}
+
+/// Train the matchboxes with the games of `schedule`, `on_evaluation` being called with the
+/// results of each evaluation as described by `training::train`
+pub fn train_menace(
+    menace: &mut Menace,
+    schedule: &Schedule,
+    on_evaluation: impl FnMut(usize, &TrainingResults),
+) -> Result<()> {
+    let data = EngineData::default();
+    let play = |agent, opponent, learn| play_menace_game(menace, agent, opponent, learn, &data);
+    training::train(schedule, play, on_evaluation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::training;
+
+    fn init() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[test]
+    fn test_matchbox_new() {
+        init();
+        let matchbox = Matchbox::new(&board::generate_new_board());
+        assert_eq!([4; 9], matchbox.beads);
+        let b = board::parse_board("XO./XO./...").unwrap();
+        let matchbox = Matchbox::new(&b);
+        assert_eq!([0, 0, 2, 0, 0, 2, 2, 2, 2], matchbox.beads);
+    }
+
+    #[test]
+    fn test_reinforce() {
+        init();
+        let mut menace = Menace::default();
+        let b = board::parse_board("XO./XO./...").unwrap();
+        let (canonical, symmetry) = board::canonical_board(&b);
+        let winning_move = board::transform_move((2, 0), symmetry);
+        for m in ai::find_all_legal_moves(&canonical) {
+            if m != winning_move {
+                menace.reinforce(&canonical, m, -5);
+            }
+        }
+        assert_eq!(1, menace.len());
+        for _ in 0..10 {
+            assert_eq!((2, 0), menace_ai(&b, Some(&menace)).unwrap().coord);
+        }
+
+        let path = std::env::temp_dir().join("tictactoe_test_menace.txt");
+        menace.save(&path).unwrap();
+        assert_eq!(menace, Menace::load(&path).unwrap());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_play_menace_game() {
+        init();
+        let mut menace = Menace::default();
+        let data = EngineData::default();
+        for _ in 0..50 {
+            play_menace_game(&mut menace, Player::PlayerX, None, true, &data).unwrap();
+        }
+        assert!(!menace.is_empty());
+        let mut play =
+            |agent, opponent, learn| play_menace_game(&mut menace, agent, opponent, learn, &data);
+        let results = training::evaluate(&mut play, Engine::RandomMove, 4).unwrap();
+        assert_eq!(4, results.wins + results.draws + results.losses);
+
+        let schedule = Schedule {
+            games: 10,
+            opponent: None,
+            eval_opponent: Engine::RandomMove,
+            eval_games: 2,
+            eval_every: 5,
+        };
+        let mut evaluations = 0;
+        train_menace(&mut menace, &schedule, |_, _| evaluations += 1).unwrap();
+        assert_eq!(2, evaluations);
+    }
+
+    #[test]
+    fn test_menace_observer() {
+        init();
+        let data = EngineData {
+            menace: Some(Mutex::default()),
+            ..Default::default()
+        };
+        let menace = data.menace.as_ref().unwrap();
+        let path = std::env::temp_dir().join("tictactoe_test_menace_observer.txt");
+        let mut observer = MenaceObserver::new(menace, Some(path.clone()));
+        let record = game::play_game(
+            Engine::Menace,
+            Engine::RandomMove,
+            Default::default(),
+            &data,
+            &mut [&mut observer],
+        )
+        .unwrap();
+        // one matchbox per move of MENACE, all different positions
+        let menace_moves = record.moves.len().div_ceil(2);
+        assert_eq!(menace_moves, menace.lock().unwrap().len());
+        assert_eq!(*menace.lock().unwrap(), Menace::load(&path).unwrap());
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,375 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Result};
+use log::info;
+
+use crate::board::{self, Board, Move};
+use crate::game::{self, Engine, EngineData};
+use crate::observer::{Output, Verbosity};
+use crate::player::{self, Player};
+use crate::render;
+
+/// Longest wait for a message : a move of a human player, or of the opponent for a client
+const READ_TIMEOUT: Duration = Duration::from_secs(600);
+/// Longest wait for a message to be sent
+pub(crate) const WRITE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Give up on a peer silent for longer than the timeouts
+fn set_timeouts(stream: &TcpStream) -> Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    Ok(())
+}
+
+/// Wait for one client on `address` and play a game against it as `side`, printing the game
+/// to `out`. Return the winner.
+pub fn serve(
+    address: &str,
+    engine: Engine,
+    side: Player,
+    data: &EngineData,
+    output: &Output,
+    out: &mut impl Write,
+) -> Result<Option<Player>> {
+    let listener = TcpListener::bind(address)?;
+    writeln!(out, "Waiting for an opponent on {}", listener.local_addr()?)?;
+    let (stream, peer) = listener.accept()?;
+    writeln!(out, "{} connected", peer)?;
+    host_game(stream, engine, side, data, output, out)
+}
+
+/// A side of a hosted game
+#[non_exhaustive]
+pub enum Seat {
+    /// An engine, or a human playing on the server
+    Local(Engine),
+    /// A client playing with the protocol of `host`
+    Remote(TcpStream),
+}
+
+/// Play a game against the client of `stream`, the server playing `side`
+pub fn host_game(
+    stream: TcpStream,
+    engine: Engine,
+    side: Player,
+    data: &EngineData,
+    output: &Output,
+    out: &mut impl Write,
+) -> Result<Option<Player>> {
+    let seats = match side {
+        Player::PlayerX => [Seat::Local(engine), Seat::Remote(stream)],
+        Player::PlayerO => [Seat::Remote(stream), Seat::Local(engine)],
+    };
+    host(seats, data, output, out)
+}
+
+/// Play a game between the seats of X and O, the server owning the board and validating
+/// every move of the clients. The protocol is made of text lines :
+/// - server to client : `WELCOME X|O` (side of the client), `BOARD <board>` (as written by
+///   `board::board_to_string`), `TURN`, `INVALID <reason>` (the client plays again) and
+///   `END X|O|DRAW`
+/// - client to server : `MOVE x,y`
+///
+/// The boards of the local moves and the result are printed to `out`.
+pub fn host(
+    seats: [Seat; 2],
+    data: &EngineData,
+    output: &Output,
+    out: &mut impl Write,
+) -> Result<Option<Player>> {
+    // engine and connection of X and of O
+    let mut sides: Vec<(Engine, Option<Connection>)> = Vec::new();
+    for (seat, player) in seats.into_iter().zip([Player::PlayerX, Player::PlayerO]) {
+        match seat {
+            Seat::Local(engine) => sides.push((engine, None)),
+            Seat::Remote(stream) => {
+                set_timeouts(&stream)?;
+                let mut writer = stream.try_clone()?;
+                send(&mut writer, &format!("WELCOME {}", side_name(&player)))?;
+                let reader = BufReader::new(stream);
+                sides.push((Engine::Human, Some(Connection { reader, writer })));
+            }
+        }
+    }
+
+    let mut board = board::generate_new_board();
+    let mut player = Player::PlayerX;
+    loop {
+        broadcast(
+            &mut sides,
+            &format!("BOARD {}", board::board_to_string(&board)),
+        )?;
+        let (engine, connection) = match player {
+            Player::PlayerX => &mut sides[0],
+            Player::PlayerO => &mut sides[1],
+        };
+        let m = match connection {
+            None => {
+                print_board(out, &board, output)?;
+                local_move(&board, &player, *engine, data)?
+            }
+            Some(Connection { reader, writer }) => {
+                send(writer, "TURN")?;
+                let line = receive(reader)?;
+                match parse_client_move(&line, &board) {
+                    Ok(m) => m,
+                    Err(e) => {
+                        send(writer, &format!("INVALID {}", e))?;
+                        continue;
+                    }
+                }
+            }
+        };
+        info!("{:?} plays {:?}", player, m);
+        board = board::make_move(&board, m, &player)?;
+
+        let winner = board::is_move_win(&board);
+        if winner.is_some() || board::is_board_full(&board) {
+            broadcast(
+                &mut sides,
+                &format!("BOARD {}", board::board_to_string(&board)),
+            )?;
+            let result = winner.as_ref().map_or("DRAW", side_name);
+            broadcast(&mut sides, &format!("END {}", result))?;
+            print_board(out, &board, output)?;
+            print_result(out, winner, output)?;
+            return Ok(winner);
+        }
+        player = game::switch_player(&player);
+    }
+}
+
+struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+/// Send a message to every client of the game
+fn broadcast(sides: &mut [(Engine, Option<Connection>)], message: &str) -> Result<()> {
+    for (_, connection) in sides.iter_mut() {
+        if let Some(Connection { writer, .. }) = connection {
+            send(writer, message)?;
+        }
+    }
+    Ok(())
+}
+
+/// Connect to a server and play the side it gives, printing the game to `out`. Return the
+/// winner.
+pub fn connect(
+    address: &str,
+    engine: Engine,
+    data: &EngineData,
+    output: &Output,
+    out: &mut impl Write,
+) -> Result<Option<Player>> {
+    let stream = TcpStream::connect(address)?;
+    join_game(stream, engine, data, output, out)
+}
+
+/// Play the game hosted by the server of `stream`
+pub fn join_game(
+    stream: TcpStream,
+    engine: Engine,
+    data: &EngineData,
+    output: &Output,
+    out: &mut impl Write,
+) -> Result<Option<Player>> {
+    set_timeouts(&stream)?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+    play_as_client(&mut reader, &mut writer, engine, data, output, out)
+}
+
+/// Play the game hosted by the server, from its `WELCOME` message to its `END`
+pub(crate) fn play_as_client(
+    reader: &mut impl BufRead,
+    writer: &mut impl Write,
+    engine: Engine,
+    data: &EngineData,
+    output: &Output,
+    out: &mut impl Write,
+) -> Result<Option<Player>> {
+    let mut side = Player::PlayerX;
+    let mut board = board::generate_new_board();
+    loop {
+        let line = receive(reader)?;
+        let (command, argument) = line.split_once(' ').unwrap_or((&line, ""));
+        match command {
+            "WELCOME" => {
+                side = parse_side(argument).ok_or_else(|| anyhow!("unknown side {}", argument))?;
+                if output.shows(Verbosity::Results) {
+                    writeln!(out, "You play {:?}", side)?;
+                }
+            }
+            "BOARD" => board = board::parse_board(argument)?,
+            "TURN" => {
+                print_board(out, &board, output)?;
+                let m = local_move(&board, &side, engine, data)?;
+                send(writer, &format!("MOVE {}", board::format_move(m)))?;
+            }
+            "INVALID" => writeln!(out, "Illegal move : {}", argument)?,
+            "END" => {
+                let winner = parse_side(argument);
+                print_board(out, &board, output)?;
+                print_result(out, winner, output)?;
+                return Ok(winner);
+            }
+            _ => bail!("unexpected message from the server : {}", line),
+        }
+    }
+}
+
+fn local_move(board: &Board, player: &Player, engine: Engine, data: &EngineData) -> Result<Move> {
+    if engine == Engine::Human {
+        return player::human_get_move(board, player, None, data);
+    }
+    Ok(game::ask_engine(board, player, &engine, data)?.coord)
+}
+
+/// Read a "MOVE x,y" line and check that the move is legal
+fn parse_client_move(line: &str, board: &Board) -> Result<Move> {
+    let Some(input) = line.strip_prefix("MOVE ") else {
+        bail!("MOVE x,y expected");
+    };
+    let m = player::get_input_from_keyboard(input)?;
+    if !board::is_valid_move(board, m) {
+        bail!("{} is already taken", board::format_move(m));
+    }
+    Ok(m)
+}
+
+fn side_name(player: &Player) -> &'static str {
+    match player {
+        Player::PlayerX => "X",
+        Player::PlayerO => "O",
+    }
+}
+
+fn parse_side(name: &str) -> Option<Player> {
+    match name {
+        "X" => Some(Player::PlayerX),
+        "O" => Some(Player::PlayerO),
+        _ => None,
+    }
+}
+
+pub(crate) fn send(writer: &mut impl Write, message: &str) -> Result<()> {
+    writeln!(writer, "{}", message)?;
+    writer.flush()?;
+    Ok(())
+}
+
+pub(crate) fn receive(reader: &mut impl BufRead) -> Result<String> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        bail!("connection closed");
+    }
+    Ok(line.trim().to_owned())
+}
+
+fn print_board(out: &mut impl Write, board: &Board, output: &Output) -> Result<()> {
+    if output.shows(Verbosity::Boards) {
+        writeln!(out, "{}", render::render(board, output.theme, None))?;
+    }
+    Ok(())
+}
+
+fn print_result(out: &mut impl Write, winner: Option<Player>, output: &Output) -> Result<()> {
+    if output.shows(Verbosity::Results) {
+        match winner {
+            Some(p) => writeln!(out, "Well done {:?} !!!", p)?,
+            None => writeln!(out, "This is a draw !")?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+    use std::thread;
+
+    fn init() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    fn silent() -> Output {
+        Output {
+            verbosity: Verbosity::Silent,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_host_and_join_game() {
+        init();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let data = EngineData::default();
+            let mut out = io::sink();
+            host_game(
+                stream,
+                Engine::Rules,
+                Player::PlayerX,
+                &data,
+                &silent(),
+                &mut out,
+            )
+            .unwrap()
+        });
+        let stream = TcpStream::connect(address).unwrap();
+        let data = EngineData::default();
+        let output = Output {
+            verbosity: Verbosity::Results,
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        let winner = join_game(stream, Engine::Rules, &data, &output, &mut out).unwrap();
+        assert_eq!(
+            "You play PlayerO\nThis is a draw !\n",
+            String::from_utf8(out).unwrap()
+        );
+        assert_eq!(None, winner);
+        assert_eq!(None, server.join().unwrap());
+    }
+
+    #[test]
+    fn test_invalid_move() {
+        init();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let data = EngineData::default();
+            host_game(
+                stream,
+                Engine::Rules,
+                Player::PlayerO,
+                &data,
+                &silent(),
+                &mut io::sink(),
+            )
+        });
+        let stream = TcpStream::connect(address).unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut writer = stream;
+        assert_eq!("WELCOME X", receive(&mut reader).unwrap());
+        assert_eq!("BOARD .../.../...", receive(&mut reader).unwrap());
+        assert_eq!("TURN", receive(&mut reader).unwrap());
+        send(&mut writer, "MOVE 3,1").unwrap();
+        assert!(receive(&mut reader).unwrap().starts_with("INVALID"));
+        assert_eq!("BOARD .../.../...", receive(&mut reader).unwrap());
+        assert_eq!("TURN", receive(&mut reader).unwrap());
+        send(&mut writer, "MOVE 1,1").unwrap();
+        assert_eq!("BOARD .../.X./...", receive(&mut reader).unwrap());
+        drop(writer);
+        drop(reader);
+        assert!(server.join().unwrap().is_err());
+    }
+}
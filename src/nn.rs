@@ -0,0 +1,509 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Result};
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::Rng;
+
+use crate::ai::{self, EngineMove, WIN_SCORE};
+use crate::board::{self, Board, Move};
+use crate::game;
+use crate::player::Player;
+use crate::solver;
+use crate::training::Model;
+
+/// Own pieces then opponent pieces, one input per case
+const INPUTS: usize = 18;
+/// 9 move logits then the value
+const OUTPUTS: usize = 10;
+
+/// Feed-forward network with one tanh hidden layer, giving for a position the probability
+/// of each move and the value (from -1, lost, to 1, won) for the player to move
+#[derive(Debug, PartialEq, Clone)]
+pub struct Network {
+    hidden: usize,
+    w1: Vec<f64>,
+    b1: Vec<f64>,
+    w2: Vec<f64>,
+    b2: Vec<f64>,
+}
+
+/// Labels of the training samples
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+#[non_exhaustive]
+pub enum Labels {
+    /// minimax best moves and value of every position
+    #[default]
+    Minimax,
+    /// results of games of the network against itself
+    SelfPlay,
+}
+
+/// Parameters of the training of a network
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct NetworkTrainingParams {
+    /// labels of the samples
+    pub labels: Labels,
+    /// number of passes over the samples
+    pub epochs: usize,
+    /// step of the gradient descent
+    pub learning_rate: f64,
+    /// number of self-play games per epoch
+    pub games: usize,
+}
+
+impl Default for NetworkTrainingParams {
+    fn default() -> Self {
+        NetworkTrainingParams {
+            labels: Labels::Minimax,
+            epochs: 100,
+            learning_rate: 0.02,
+            games: 200,
+        }
+    }
+}
+
+/// Training example
+#[derive(Debug, PartialEq, Clone)]
+pub struct Sample {
+    /// position, with the player to move given by the piece counts
+    pub board: Board,
+    /// probability of each move (index y*3+x), summing to 1
+    pub policy: [f64; 9],
+    /// expected value for the player to move, from -1 to 1
+    pub value: f64,
+    /// weight of the policy in the loss
+    pub policy_weight: f64,
+}
+
+impl Network {
+    /// Network of `hidden` neurons with random weights
+    pub fn new(hidden: usize, rng: &mut impl Rng) -> Network {
+        let mut init = |n: usize, fan_in: usize| -> Vec<f64> {
+            let bound = 1.0 / (fan_in as f64).sqrt();
+            (0..n).map(|_| rng.gen_range(-bound..bound)).collect()
+        };
+        Network {
+            hidden,
+            w1: init(hidden * INPUTS, INPUTS),
+            b1: vec![0.0; hidden],
+            w2: init(OUTPUTS * hidden, hidden),
+            b2: vec![0.0; OUTPUTS],
+        }
+    }
+
+    /// Return the input vector, the hidden layer, the move probabilities (0 for illegal
+    /// moves) and the value
+    fn forward_layers(&self, board: &Board) -> ([f64; INPUTS], Vec<f64>, [f64; 9], f64) {
+        let x = encode_board(board);
+        let h: Vec<f64> = (0..self.hidden)
+            .map(|j| {
+                let row = &self.w1[j * INPUTS..(j + 1) * INPUTS];
+                (self.b1[j] + row.iter().zip(x.iter()).map(|(w, x)| w * x).sum::<f64>()).tanh()
+            })
+            .collect();
+        let o: Vec<f64> = (0..OUTPUTS)
+            .map(|k| {
+                let row = &self.w2[k * self.hidden..(k + 1) * self.hidden];
+                self.b2[k] + row.iter().zip(h.iter()).map(|(w, h)| w * h).sum::<f64>()
+            })
+            .collect();
+
+        let legal: Vec<usize> = ai::find_all_legal_moves(board)
+            .iter()
+            .map(|m| m.0 * 3 + m.1)
+            .collect();
+        let mut policy = [0.0; 9];
+        let max = legal.iter().map(|i| o[*i]).fold(f64::MIN, f64::max);
+        let total: f64 = legal.iter().map(|i| (o[*i] - max).exp()).sum();
+        for i in &legal {
+            policy[*i] = (o[*i] - max).exp() / total;
+        }
+        (x, h, policy, o[9].tanh())
+    }
+
+    /// Move probabilities (index y*3+x) and value for the player to move
+    pub fn forward(&self, board: &Board) -> ([f64; 9], f64) {
+        let (_, _, policy, value) = self.forward_layers(board);
+        (policy, value)
+    }
+
+    /// One step of gradient descent on a sample, return the loss before the step
+    fn train_sample(&mut self, sample: &Sample, learning_rate: f64) -> f64 {
+        let (x, h, policy, value) = self.forward_layers(&sample.board);
+        let mut loss = (value - sample.value).powi(2);
+        let mut d_o = [0.0; OUTPUTS];
+        for i in 0..9 {
+            if policy[i] > 0.0 {
+                loss -= sample.policy_weight * sample.policy[i] * policy[i].ln();
+                d_o[i] = sample.policy_weight * (policy[i] - sample.policy[i]);
+            }
+        }
+        d_o[9] = 2.0 * (value - sample.value) * (1.0 - value * value);
+
+        let mut d_h = vec![0.0; self.hidden];
+        for (k, d) in d_o.iter().enumerate() {
+            for j in 0..self.hidden {
+                d_h[j] += self.w2[k * self.hidden + j] * d;
+                self.w2[k * self.hidden + j] -= learning_rate * d * h[j];
+            }
+            self.b2[k] -= learning_rate * d;
+        }
+        for j in 0..self.hidden {
+            let d = d_h[j] * (1.0 - h[j] * h[j]);
+            let row = &mut self.w1[j * INPUTS..(j + 1) * INPUTS];
+            for (w, x) in row.iter_mut().zip(x.iter()) {
+                *w -= learning_rate * d * x;
+            }
+            self.b1[j] -= learning_rate * d;
+        }
+        loss
+    }
+
+    /// Train on the samples in a random order, return the mean loss of each epoch
+    pub fn train(
+        &mut self,
+        samples: &[Sample],
+        epochs: usize,
+        learning_rate: f64,
+        rng: &mut impl Rng,
+    ) -> Vec<f64> {
+        let mut order: Vec<usize> = (0..samples.len()).collect();
+        let mut losses = Vec::new();
+        for _ in 0..epochs {
+            rand::seq::SliceRandom::shuffle(order.as_mut_slice(), rng);
+            let total: f64 = order
+                .iter()
+                .map(|i| self.train_sample(&samples[*i], learning_rate))
+                .sum();
+            losses.push(total / samples.len() as f64);
+        }
+        losses
+    }
+
+    /// Most probable move
+    pub fn best_move(&self, board: &Board) -> Option<Move> {
+        let (policy, _) = self.forward(board);
+        ai::find_all_legal_moves(board)
+            .into_iter()
+            .max_by(|a, b| policy[a.0 * 3 + a.1].total_cmp(&policy[b.0 * 3 + b.1]))
+    }
+
+    /// Share of the samples where the most probable move is one of the target moves
+    pub fn accuracy(&self, samples: &[Sample]) -> f64 {
+        let correct = samples
+            .iter()
+            .filter(|s| {
+                self.best_move(&s.board)
+                    .is_some_and(|m| s.policy[m.0 * 3 + m.1] > 0.0)
+            })
+            .count();
+        correct as f64 / samples.len() as f64
+    }
+
+    /// Text format : `tictactoe-nn <hidden>` then one line per weight vector
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let line = |v: &[f64]| {
+            v.iter()
+                .map(|w| w.to_string())
+                .collect::<Vec<String>>()
+                .join(" ")
+        };
+        let output = format!(
+            "tictactoe-nn {}\n{}\n{}\n{}\n{}\n",
+            self.hidden,
+            line(&self.w1),
+            line(&self.b1),
+            line(&self.w2),
+            line(&self.b2)
+        );
+        fs::write(path, output)?;
+        Ok(())
+    }
+
+    /// Read a network written by `save`
+    pub fn load(path: &Path) -> Result<Network> {
+        let content = fs::read_to_string(path)?;
+        let mut lines = content.lines();
+        let hidden: usize = match lines.next().and_then(|l| l.strip_prefix("tictactoe-nn ")) {
+            Some(h) => h.trim().parse()?,
+            None => bail!("Not a network file"),
+        };
+        // each weight is written with at least one digit and a separator
+        let weights = hidden
+            .checked_mul(INPUTS + 1 + OUTPUTS)
+            .and_then(|w| w.checked_add(OUTPUTS));
+        if hidden == 0 || weights.is_none_or(|w| w > content.len() / 2) {
+            bail!(
+                "Network of {} hidden neurons too large for its file",
+                hidden
+            );
+        }
+        let mut read = |len: usize| -> Result<Vec<f64>> {
+            let v: Vec<f64> = lines
+                .next()
+                .unwrap_or_default()
+                .split_whitespace()
+                .map(|w| w.parse())
+                .collect::<Result<_, _>>()?;
+            if v.len() != len {
+                bail!("{} weights expected, {} found", len, v.len());
+            }
+            if v.iter().any(|w| !w.is_finite()) {
+                bail!("Weight not finite");
+            }
+            Ok(v)
+        };
+        Ok(Network {
+            hidden,
+            w1: read(hidden * INPUTS)?,
+            b1: read(hidden)?,
+            w2: read(OUTPUTS * hidden)?,
+            b2: read(OUTPUTS)?,
+        })
+    }
+}
+
+/// Inputs from the point of view of the player to move
+fn encode_board(board: &Board) -> [f64; INPUTS] {
+    let own = match board::player_to_move(board) {
+        Player::PlayerX => 'X',
+        Player::PlayerO => 'O',
+    };
+    let mut x = [0.0; INPUTS];
+    for (i, c) in board.iter().flatten().enumerate() {
+        match c {
+            Some(p) if *p == own => x[i] = 1.0,
+            Some(_) => x[9 + i] = 1.0,
+            None => {}
+        }
+    }
+    x
+}
+
+/// Every reachable position where the game is not over, labelled with its minimax best
+/// moves and value
+pub fn minimax_samples() -> Vec<Sample> {
+    let mut cache: HashMap<u64, i8> = HashMap::new();
+    let mut positions: Vec<Board> = solver::reachable_positions()
+        .into_iter()
+        .filter(|b| board::is_move_win(b).is_none() && !board::is_board_full(b))
+        .collect();
+    positions.sort();
+    positions
+        .into_iter()
+        .map(|b| {
+            let player = board::player_to_move(&b);
+            let scores = ai::minimax_scores(&b, &player, &mut cache);
+            let sign = match player {
+                Player::PlayerX => 1,
+                Player::PlayerO => -1,
+            };
+            let best = scores.iter().map(|s| s.0 * sign).max().unwrap();
+            let best_moves: Vec<Move> = scores
+                .iter()
+                .filter(|s| s.0 * sign == best)
+                .map(|s| s.1)
+                .collect();
+            let mut policy = [0.0; 9];
+            for m in &best_moves {
+                policy[m.0 * 3 + m.1] = 1.0 / best_moves.len() as f64;
+            }
+            Sample {
+                board: b,
+                policy,
+                value: f64::from(best.signum()),
+                policy_weight: 1.0,
+            }
+        })
+        .collect()
+}
+
+/// Play `games` games of the network against itself, drawing the moves from its policy.
+/// Every position is labelled with the result of the game and the move played, the moves
+/// of the loser being ignored and the moves of a draw counting half. Fail if the policy of
+/// the network is not a probability distribution, like after a diverging training.
+pub fn self_play_samples(
+    network: &Network,
+    games: usize,
+    rng: &mut impl Rng,
+) -> Result<Vec<Sample>> {
+    let mut samples = Vec::new();
+    for _ in 0..games {
+        let mut board = board::generate_new_board();
+        let mut player = Player::PlayerX;
+        let mut played: Vec<(Board, Move, Player)> = Vec::new();
+        let winner = loop {
+            let (policy, _) = network.forward(&board);
+            let index = WeightedIndex::new(policy)
+                .map_err(|e| anyhow!("invalid policy of the network : {}", e))?
+                .sample(rng);
+            let m = (index / 3, index % 3);
+            played.push((board, m, player));
+            board = board::make_move(&board, m, &player)?;
+            if board::is_move_win(&board).is_some() || board::is_board_full(&board) {
+                break board::is_move_win(&board);
+            }
+            player = game::switch_player(&player);
+        };
+        for (b, m, p) in played {
+            let (value, policy_weight) = match winner {
+                Some(w) if w == p => (1.0, 1.0),
+                Some(_) => (-1.0, 0.0),
+                None => (0.0, 0.5),
+            };
+            let mut policy = [0.0; 9];
+            policy[m.0 * 3 + m.1] = 1.0;
+            samples.push(Sample {
+                board: b,
+                policy,
+                value,
+                policy_weight,
+            });
+        }
+    }
+    Ok(samples)
+}
+
+impl Model for Network {
+    fn load(path: &Path) -> Result<Network> {
+        Network::load(path)
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        Network::save(self, path)
+    }
+
+    fn summary(&self) -> String {
+        format!("network of {} hidden neurons", self.hidden)
+    }
+}
+
+/// Train the network one epoch at a time, on the minimax samples or on new self-play games.
+/// `on_epoch` is called with the epoch number, its mean loss and the share of positions where
+/// the network plays a minimax best move. Fail as `self_play_samples` does.
+pub fn train_network(
+    network: &mut Network,
+    params: &NetworkTrainingParams,
+    rng: &mut impl Rng,
+    mut on_epoch: impl FnMut(usize, f64, f64),
+) -> Result<()> {
+    let minimax_samples = minimax_samples();
+    for epoch in 0..params.epochs {
+        let loss = match params.labels {
+            Labels::SelfPlay => {
+                let samples = self_play_samples(network, params.games, rng)?;
+                network.train(&samples, 1, params.learning_rate, rng)[0]
+            }
+            Labels::Minimax => network.train(&minimax_samples, 1, params.learning_rate, rng)[0],
+        };
+        on_epoch(epoch + 1, loss, network.accuracy(&minimax_samples));
+    }
+    Ok(())
+}
+
+/// Play the most probable move of the network, randomly without a network
+pub fn neural_ai(board: &Board, network: Option<&Network>) -> Result<EngineMove> {
+    let Some(network) = network else {
+        return ai::random_ai(board);
+    };
+    let m = network
+        .best_move(board)
+        .ok_or_else(|| anyhow!("no legal move available"))?;
+    let new_board = board::make_move(board, m, &board::player_to_move(board))?;
+    // the value of the next position is seen by the opponent
+    let (_, value) = network.forward(&new_board);
+    let score = match board::player_to_move(board) {
+        Player::PlayerX => -value,
+        Player::PlayerO => value,
+    };
+    Ok(EngineMove {
+        coord: m,
+        score: Some((score * f64::from(WIN_SCORE)).round() as i8),
+        pv: vec![m],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn init() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[test]
+    fn test_encode_board() {
+        init();
+        let b = board::parse_board("X.O/.../...").unwrap();
+        let x = encode_board(&b);
+        // X to move
+        assert_eq!(1.0, x[0]);
+        assert_eq!(1.0, x[9 + 2]);
+        assert_eq!(2.0, x.iter().sum::<f64>());
+    }
+
+    #[test]
+    fn test_forward() {
+        init();
+        let network = Network::new(8, &mut StdRng::seed_from_u64(1));
+        let b = board::parse_board("XO./XO./...").unwrap();
+        let (policy, value) = network.forward(&b);
+        assert!((policy.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        assert_eq!(0.0, policy[0]);
+        assert!((-1.0..=1.0).contains(&value));
+    }
+
+    #[test]
+    fn test_train() {
+        init();
+        let mut rng = StdRng::seed_from_u64(1);
+        let samples: Vec<Sample> = minimax_samples().into_iter().take(300).collect();
+        let mut network = Network::new(16, &mut rng);
+        let losses = network.train(&samples, 20, 0.05, &mut rng);
+        assert!(losses[19] < losses[0]);
+
+        let path = std::env::temp_dir().join("tictactoe_test_network.txt");
+        network.save(&path).unwrap();
+        assert_eq!(network, Network::load(&path).unwrap());
+        for invalid in ["tictactoe-nn 0\n", "tictactoe-nn 18446744073709551615\n"] {
+            fs::write(&path, invalid).unwrap();
+            assert!(Network::load(&path).is_err());
+        }
+        fs::remove_file(path).unwrap();
+
+        let self_play = self_play_samples(&network, 5, &mut rng).unwrap();
+        assert!(self_play.len() >= 25);
+        let m = neural_ai(&board::generate_new_board(), Some(&network)).unwrap();
+        assert!(m.score.is_some());
+    }
+
+    #[test]
+    fn test_train_network() {
+        init();
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut network = Network::new(8, &mut rng);
+        let params = NetworkTrainingParams {
+            labels: Labels::SelfPlay,
+            epochs: 2,
+            games: 3,
+            ..Default::default()
+        };
+        let mut epochs = Vec::new();
+        train_network(&mut network, &params, &mut rng, |epoch, _, accuracy| {
+            assert!((0.0..=1.0).contains(&accuracy));
+            epochs.push(epoch);
+        })
+        .unwrap();
+        assert_eq!(vec![1, 2], epochs);
+
+        let diverging = NetworkTrainingParams {
+            learning_rate: f64::NAN,
+            ..params
+        };
+        assert!(train_network(&mut network, &diverging, &mut rng, |_, _, _| {}).is_err());
+    }
+}
@@ -0,0 +1,189 @@
+use std::fmt::Display;
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use log::error;
+
+use crate::ai::EngineMove;
+use crate::board::{Board, Move};
+use crate::game::{self, Engine, GameRecord};
+use crate::player::Player;
+use crate::render::{self, Theme};
+
+/// Why a game ended
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[non_exhaustive]
+pub enum EndReason {
+    /// Three in a row
+    Line,
+    /// Full board without a line
+    Draw,
+    /// The loser's flag fell
+    Time,
+}
+
+/// A move just played
+#[derive(Debug, PartialEq, Clone)]
+pub struct MoveEvent {
+    /// player of the move
+    pub player: Player,
+    /// engine of the player
+    pub engine: Engine,
+    /// move and its evaluation by the engine
+    pub engine_move: EngineMove,
+    /// Board after the move
+    pub board: Board,
+    /// Thinking time of the move
+    pub elapsed: Duration,
+    /// Time left on the player's clock, None without time control
+    pub remaining: Option<Duration>,
+}
+
+/// Listener of the game loop, and interface of the human players for the observers
+/// implementing `human_move`. Every method does nothing by default.
+pub trait GameObserver {
+    /// Called before the first move, with the engines of X and O and the empty board
+    fn on_game_started(&mut self, _engine_x: Engine, _engine_o: Engine, _board: &Board) {}
+
+    /// Called after each move
+    fn on_move_played(&mut self, _event: &MoveEvent) {}
+
+    /// Called once the game is over, with its moves and winner
+    fn on_game_ended(&mut self, _record: &GameRecord, _reason: EndReason) {}
+
+    /// Move of a human player, asked before each of their moves with its deadline under a
+    /// time control. None lets the next observers, then the standard input, give it.
+    fn human_move(
+        &mut self,
+        _board: &Board,
+        _player: &Player,
+        _deadline: Option<Instant>,
+    ) -> Option<Result<Move>> {
+        None
+    }
+}
+
+/// How much is printed while playing, each level printing what the previous ones print
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Default)]
+#[non_exhaustive]
+pub enum Verbosity {
+    /// Nothing
+    Silent,
+    /// The final score of the match
+    Summary,
+    /// The result of each game
+    Results,
+    /// Every board
+    #[default]
+    Boards,
+}
+
+/// Output policy of the games
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct Output {
+    /// what is printed
+    pub verbosity: Verbosity,
+    /// how the boards are printed
+    pub theme: Theme,
+}
+
+impl Output {
+    /// Whether what is printed at `verbosity` is printed
+    pub fn shows(&self, verbosity: Verbosity) -> bool {
+        self.verbosity >= verbosity
+    }
+}
+
+/// Print the boards and results to `out` (the standard output for the command line),
+/// following the output policy
+#[derive(Debug)]
+pub struct ConsoleObserver<W: Write> {
+    /// output policy
+    pub output: Output,
+    /// where the games are printed
+    pub out: W,
+}
+
+impl<W: Write> ConsoleObserver<W> {
+    /// Print a line, the game going on if the output fails
+    fn print(&mut self, line: impl Display) {
+        if let Err(e) = writeln!(self.out, "{}", line) {
+            error!("Error printing the game : {}", e);
+        }
+    }
+}
+
+impl<W: Write> GameObserver for ConsoleObserver<W> {
+    fn on_game_started(&mut self, _engine_x: Engine, _engine_o: Engine, board: &Board) {
+        if self.output.shows(Verbosity::Boards) {
+            self.print(render::render(board, self.output.theme, None));
+        }
+    }
+
+    fn on_move_played(&mut self, event: &MoveEvent) {
+        if self.output.shows(Verbosity::Boards) {
+            let last_move = Some(event.engine_move.coord);
+            self.print(render::render(&event.board, self.output.theme, last_move));
+        }
+    }
+
+    fn on_game_ended(&mut self, record: &GameRecord, reason: EndReason) {
+        if !self.output.shows(Verbosity::Results) {
+            return;
+        }
+        match (record.winner, reason) {
+            (Some(p), EndReason::Time) => {
+                self.print(format!("{:?} lost on time !", game::switch_player(&p)));
+                self.print(format!("Well done {:?} !!!", p));
+            }
+            (Some(p), _) => self.print(format!("Well done {:?} !!!", p)),
+            (None, _) => self.print("This is a draw !"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn init() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[test]
+    fn test_output_shows() {
+        init();
+        let output = Output {
+            verbosity: Verbosity::Results,
+            ..Default::default()
+        };
+        assert!(output.shows(Verbosity::Summary));
+        assert!(output.shows(Verbosity::Results));
+        assert!(!output.shows(Verbosity::Boards));
+        assert!(Output::default().shows(Verbosity::Boards));
+    }
+
+    #[test]
+    fn test_console_observer() {
+        init();
+        let output = Output {
+            verbosity: Verbosity::Results,
+            ..Default::default()
+        };
+        let mut console = ConsoleObserver {
+            output,
+            out: Vec::new(),
+        };
+        let record = GameRecord {
+            moves: vec![(1, 1)],
+            winner: Some(Player::PlayerX),
+        };
+        console.on_game_started(Engine::Rules, Engine::Rules, &Board::default());
+        console.on_game_ended(&record, EndReason::Time);
+        assert_eq!(
+            "PlayerO lost on time !\nWell done PlayerX !!!\n",
+            String::from_utf8(console.out).unwrap()
+        );
+    }
+}
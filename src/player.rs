@@ -1,53 +1,146 @@
 use anyhow::{bail, Result};
 use log::{debug, info};
-use std::io::{self, BufRead};
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::time::Instant;
 
-use crate::board::{self, Board};
+use crate::board::{self, Board, Move};
+use crate::game::{self, EngineData};
+use crate::{ai, rules};
 
+/// Serialized as "X" or "O" with the `serde` feature
 #[derive(PartialEq, Debug, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Player {
+    /// Plays first
+    #[cfg_attr(feature = "serde", serde(rename = "X"))]
     PlayerX,
+    /// Plays second
+    #[cfg_attr(feature = "serde", serde(rename = "O"))]
     PlayerO,
 }
 
-pub fn print_player_input_rule() {
-    debug!("Print player input rules");
+/// How a human types a move and the other commands, shown before the game
+pub fn player_input_rule() -> String {
+    debug!("Player input rules");
 
-    println!("\nPlease input your move. Format : x_coord (from 0 to 2) , y_coord (from 0 to 2)");
-    println!("Example : > 1,2");
-    println!("Coordinates : ");
     let board_example =
         "  0 1 2\n".to_owned() + " -------\n" + "0      \n" + "1      \n" + "2      \n";
-    println!("{}", board_example);
+    format!(
+        "\nPlease input your move. Format : x_coord (from 0 to 2) , y_coord (from 0 to 2)\n\
+         Example : > 1,2\n\
+         Coordinates : \n\
+         {}\n\
+         Type \"hint\" (or \"hint <engine>\") for a recommended move,\n\
+         and \"explain\" for the minimax value of every legal move.\n",
+        board_example
+    )
 }
 
-fn get_move(input: &mut impl BufRead, board: &Board, active_player: &Player) -> Result<Board> {
+fn get_move(
+    input: &mut impl BufRead,
+    out: &mut impl Write,
+    board: &Board,
+    active_player: &Player,
+    data: &EngineData,
+) -> Result<Move> {
     info!("Get player's move from keyboard");
     loop {
         let player_input = input.lines().next().unwrap()?;
+        let hint = match player_input.trim() {
+            "hint" => Some(""),
+            command => command.strip_prefix("hint "),
+        };
+        if let Some(engine_name) = hint {
+            print_hint(out, board, active_player, engine_name.trim(), data)?;
+            continue;
+        }
+        if player_input.trim() == "explain" {
+            print_explanation(out, board, active_player)?;
+            continue;
+        }
         let player_move = match get_input_from_keyboard(&player_input) {
             Ok(player_move) => player_move,
             Err(e) => {
-                println!("Error : {} \nTry again", e);
+                writeln!(out, "Error : {} \nTry again", e)?;
                 continue;
             }
         };
         if board::is_valid_move(board, player_move) {
-            let new_board = board::make_move(board, player_move, active_player).unwrap();
-            //let output = board::render_board(new_board).unwrap();
-            //println!("{}", output);
-            return Ok(new_board);
+            return Ok(player_move);
         } else {
-            println!("Illegal move - try again");
+            writeln!(out, "Illegal move - try again")?;
         }
     }
 }
 
-pub fn human_get_move(board: &Board, active_player: &Player) -> Result<Board> {
-    get_move(&mut io::stdin().lock(), board, active_player)
+/// Ask the human at the terminal for a move, reading the standard input and answering on the
+/// standard output. A `deadline` is announced before the move.
+pub fn human_get_move(
+    board: &Board,
+    active_player: &Player,
+    deadline: Option<Instant>,
+    data: &EngineData,
+) -> Result<Move> {
+    let mut out = io::stdout();
+    if let Some(deadline) = deadline {
+        let budget = deadline.saturating_duration_since(Instant::now());
+        let seconds = budget.as_secs_f64();
+        writeln!(out, "{:?}, you have {:.1}s to play", active_player, seconds)?;
+    }
+    get_move(
+        &mut io::stdin().lock(),
+        &mut out,
+        board,
+        active_player,
+        data,
+    )
 }
 
-fn get_input_from_keyboard(player_input: &str) -> Result<(usize, usize)> {
+/// Print the move recommended by the engine named `engine_name` (MinMax if empty)
+fn print_hint(
+    out: &mut impl Write,
+    board: &Board,
+    active_player: &Player,
+    engine_name: &str,
+    data: &EngineData,
+) -> Result<()> {
+    let engine_name = if engine_name.is_empty() {
+        "MinMax"
+    } else {
+        engine_name
+    };
+    let Some(engine) = game::engine_from_name(engine_name) else {
+        writeln!(out, "Unknown engine {}", engine_name)?;
+        return Ok(());
+    };
+    match game::ask_engine(board, active_player, &engine, data) {
+        Ok(m) => writeln!(
+            out,
+            "Hint from {} : {}",
+            engine_name,
+            board::format_move(m.coord)
+        )?,
+        Err(e) => writeln!(out, "Error : {}", e)?,
+    }
+    Ok(())
+}
+
+/// Print the outcome of every legal move with perfect play from both players
+fn print_explanation(out: &mut impl Write, board: &Board, active_player: &Player) -> Result<()> {
+    let empty_cases = ai::find_all_legal_moves(board).len();
+    for (score, m) in ai::minimax_scores(board, active_player, &mut HashMap::new()) {
+        let outcome = ai::outcome(score, active_player, empty_cases);
+        writeln!(out, "{} : {}", board::format_move(m), outcome)?;
+    }
+    if let Some((m, rule)) = rules::rule_move(board, active_player) {
+        writeln!(out, "Rule : {} ({})", rule, board::format_move(m))?;
+    }
+    Ok(())
+}
+
+/// Parse a move typed as "x,y"
+pub fn get_input_from_keyboard(player_input: &str) -> Result<Move> {
     let mut player_move: (usize, usize) = (usize::MAX, usize::MAX);
     for s in player_input.trim().split(',') {
         if player_move.0 == usize::MAX {
@@ -103,19 +196,51 @@ mod tests {
 
     #[test]
     fn test_get_move() {
-        let mut board: Board = [
+        let board: Board = [
             [Some('X'), None, Some('O')],
             [None, Some('O'), Some('X')],
             [Some('X'), None, Some('O')],
         ];
+        let data = EngineData::default();
+        let mut out = Vec::new();
         let mut input = "1,2\n".as_bytes();
-        let new_board = get_move(&mut input, &board, &Player::PlayerX).unwrap();
-        board[2][1] = Some('X');
-        assert_eq!(board, new_board);
-
-        input = "1,0\n".as_bytes();
-        let new_board = get_move(&mut input, &board, &Player::PlayerO).unwrap();
-        board[0][1] = Some('O');
-        assert_eq!(board, new_board);
+        assert_eq!(
+            (2, 1),
+            get_move(&mut input, &mut out, &board, &Player::PlayerX, &data).unwrap()
+        );
+
+        input = "0,0\n1,0\n".as_bytes();
+        assert_eq!(
+            (0, 1),
+            get_move(&mut input, &mut out, &board, &Player::PlayerO, &data).unwrap()
+        );
+        assert_eq!(
+            "Illegal move - try again\n",
+            String::from_utf8(out).unwrap()
+        );
+
+        let mut out = Vec::new();
+        input = "hint\nhint RandomMove\nhint Human\nhintfoo\nexplain\n1,0\n".as_bytes();
+        assert_eq!(
+            (0, 1),
+            get_move(&mut input, &mut out, &board, &Player::PlayerO, &data).unwrap()
+        );
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("Hint from MinMax : "));
+        assert!(out.contains("Error : a human engine can't be asked for a move"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_json_shape() {
+        init();
+        assert_eq!("\"X\"", serde_json::to_string(&Player::PlayerX).unwrap());
+        assert_eq!("\"O\"", serde_json::to_string(&Player::PlayerO).unwrap());
+        assert_eq!(
+            Player::PlayerO,
+            serde_json::from_str::<Player>("\"O\"").unwrap()
+        );
+        assert!(serde_json::from_str::<Player>("\"Z\"").is_err());
+        assert!(serde_json::from_str::<Player>("\"x\"").is_err());
     }
 }
@@ -0,0 +1,312 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Result};
+use rand::Rng;
+
+use crate::ai::{self, EngineMove};
+use crate::board::{self, Board, Move};
+use crate::game::{self, Engine, EngineData};
+use crate::player::Player;
+use crate::training::{self, Model, Schedule, TrainingResults};
+
+/// Parameters of the Q-learning updates
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct QLearningParams {
+    /// weight of a new estimate of a value
+    pub learning_rate: f64,
+    /// factor of the value of the next position
+    pub discount: f64,
+    /// probability to explore a random move during training
+    pub epsilon: f64,
+}
+
+impl Default for QLearningParams {
+    fn default() -> Self {
+        QLearningParams {
+            learning_rate: 0.5,
+            discount: 0.9,
+            epsilon: 0.1,
+        }
+    }
+}
+
+/// Value of each move (index y*3+x) of a position for the player to move : 1 for a win,
+/// -1 for a loss
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct QTable {
+    values: HashMap<Board, [f64; 9]>,
+}
+
+impl QTable {
+    /// Number of positions learned
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Whether no position was learned yet
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Value of the move `m`, 0 if not learned
+    pub fn value(&self, board: &Board, m: Move) -> f64 {
+        self.values.get(board).map_or(0.0, |v| v[m.0 * 3 + m.1])
+    }
+
+    /// Value of the best legal move, 0 if the game is over
+    fn best_value(&self, board: &Board) -> f64 {
+        ai::find_all_legal_moves(board)
+            .into_iter()
+            .map(|m| self.value(board, m))
+            .reduce(f64::max)
+            .unwrap_or(0.0)
+    }
+
+    /// Return the best legal move, a random one between equal values
+    pub fn best_move(&self, board: &Board) -> Option<Move> {
+        let best_value = self.best_value(board);
+        let best_moves: Vec<Move> = ai::find_all_legal_moves(board)
+            .into_iter()
+            .filter(|m| self.value(board, *m) == best_value)
+            .collect();
+        if best_moves.is_empty() {
+            return None;
+        }
+        Some(best_moves[rand::thread_rng().gen_range(0..best_moves.len())])
+    }
+
+    /// Epsilon-greedy choice
+    fn choose_move(&self, board: &Board, epsilon: f64) -> Option<Move> {
+        let mut rng = rand::thread_rng();
+        if rng.gen_bool(epsilon) {
+            let legal_moves = ai::find_all_legal_moves(board);
+            return Some(legal_moves[rng.gen_range(0..legal_moves.len())]);
+        }
+        self.best_move(board)
+    }
+
+    fn update(&mut self, board: &Board, m: Move, target: f64, learning_rate: f64) {
+        let value = &mut self.values.entry(*board).or_insert([0.0; 9])[m.0 * 3 + m.1];
+        *value += learning_rate * (target - *value);
+    }
+
+    /// Text format : one position per line, the board as read by `board::parse_board`
+    /// followed by the 9 values
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let mut boards: Vec<&Board> = self.values.keys().collect();
+        boards.sort();
+        let mut output = String::new();
+        for b in boards {
+            let values: Vec<String> = self.values[b].iter().map(|v| v.to_string()).collect();
+            output += &format!("{} {}\n", board::board_to_string(b), values.join(" "));
+        }
+        fs::write(path, output)?;
+        Ok(())
+    }
+
+    /// Read a table written by `save`, whose values must be finite
+    pub fn load(path: &Path) -> Result<QTable> {
+        let mut values = HashMap::new();
+        for line in fs::read_to_string(path)?.lines() {
+            let mut fields = line.split_whitespace();
+            let b = board::parse_board(fields.next().unwrap_or_default())?;
+            let v: Vec<f64> = fields.map(|f| f.parse()).collect::<Result<_, _>>()?;
+            if v.len() != 9 {
+                bail!("9 values expected for {}", board::board_to_string(&b));
+            }
+            if v.iter().any(|q| !q.is_finite()) {
+                bail!("Q-value not finite for {}", board::board_to_string(&b));
+            }
+            values.insert(b, v.try_into().unwrap());
+        }
+        Ok(QTable { values })
+    }
+}
+
+/// Play a game where the learning engine plays `agent` against `opponent`, or both sides
+/// when `opponent` is None. The table is updated after each move if `learn` is set,
+/// otherwise the engine always plays its best move. Return the winner.
+pub fn play_qlearning_game(
+    table: &mut QTable,
+    agent: Player,
+    opponent: Option<Engine>,
+    params: &QLearningParams,
+    learn: bool,
+    data: &EngineData,
+) -> Result<Option<Player>> {
+    let epsilon = if learn { params.epsilon } else { 0.0 };
+    let mut board = board::generate_new_board();
+    let mut player = Player::PlayerX;
+    // last move of X and of O, waiting for the value of the position that follows
+    let mut pending: [Option<(Board, Move)>; 2] = [None, None];
+    let index = |p: Player| match p {
+        Player::PlayerX => 0,
+        Player::PlayerO => 1,
+    };
+
+    loop {
+        let m = match opponent {
+            Some(engine) if player != agent => {
+                game::ask_engine(&board, &player, &engine, data)?.coord
+            }
+            _ => {
+                let m = table
+                    .choose_move(&board, epsilon)
+                    .ok_or_else(|| anyhow!("no legal move available"))?;
+                if learn {
+                    if let Some((b, previous)) = pending[index(player)] {
+                        let target = params.discount * table.best_value(&board);
+                        table.update(&b, previous, target, params.learning_rate);
+                    }
+                    pending[index(player)] = Some((board, m));
+                }
+                m
+            }
+        };
+        board = board::make_move(&board, m, &player)?;
+
+        let winner = board::is_move_win(&board);
+        if winner.is_some() || board::is_board_full(&board) {
+            for p in [Player::PlayerX, Player::PlayerO] {
+                if let Some((b, previous)) = pending[index(p)] {
+                    let reward = match winner {
+                        Some(w) if w == p => 1.0,
+                        Some(_) => -1.0,
+                        None => 0.0,
+                    };
+                    table.update(&b, previous, reward, params.learning_rate);
+                }
+            }
+            return Ok(winner);
+        }
+        player = game::switch_player(&player);
+    }
+}
+
+impl Model for QTable {
+    fn load(path: &Path) -> Result<QTable> {
+        QTable::load(path)
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        QTable::save(self, path)
+    }
+
+    fn summary(&self) -> String {
+        format!("{} positions", self.len())
+    }
+}
+
+/// Play the best move of the table, a random one for unknown positions
+pub fn qlearning_ai(board: &Board, table: Option<&QTable>) -> Result<EngineMove> {
+    let m = match table {
+        Some(t) => t.best_move(board),
+        None => QTable::default().best_move(board),
+    };
+    match m {
+        Some(m) => Ok(m.into()),
+        None => Err(anyhow!("no legal move available")),
+    }
+}
+
+/// Train the table with the games of `schedule`, `on_evaluation` being called with the
+/// results of each evaluation as described by `training::train`. Fail if a parameter is
+/// not between 0 and 1.
+pub fn train_qtable(
+    table: &mut QTable,
+    schedule: &Schedule,
+    params: &QLearningParams,
+    on_evaluation: impl FnMut(usize, &TrainingResults),
+) -> Result<()> {
+    for p in [params.learning_rate, params.discount, params.epsilon] {
+        if !(0.0..=1.0).contains(&p) {
+            bail!("the Q-learning parameters are between 0 and 1");
+        }
+    }
+    let data = EngineData::default();
+    let play =
+        |agent, opponent, learn| play_qlearning_game(table, agent, opponent, params, learn, &data);
+    training::train(schedule, play, on_evaluation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::training;
+
+    fn init() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[test]
+    fn test_update() {
+        init();
+        let mut table = QTable::default();
+        let b = board::parse_board("XX./OO./...").unwrap();
+        table.update(&b, (0, 2), 1.0, 0.5);
+        assert_eq!(0.5, table.value(&b, (0, 2)));
+        table.update(&b, (0, 2), 1.0, 0.5);
+        assert_eq!(0.75, table.value(&b, (0, 2)));
+        assert_eq!(0.75, table.best_value(&b));
+        assert_eq!(Some((0, 2)), table.best_move(&b));
+        assert_eq!(0.0, table.value(&b, (2, 2)));
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        init();
+        let mut table = QTable::default();
+        let b = board::parse_board("XX./OO./...").unwrap();
+        table.update(&b, (0, 2), 1.0, 0.5);
+        table.update(&board::generate_new_board(), (1, 1), -0.25, 1.0);
+        let path = std::env::temp_dir().join("tictactoe_test_qtable.txt");
+        table.save(&path).unwrap();
+        assert_eq!(table, QTable::load(&path).unwrap());
+        fs::write(&path, "XX./OO./... 0 0 NaN 0 0 0 0 0 0\n").unwrap();
+        assert!(QTable::load(&path).is_err());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_play_qlearning_game() {
+        init();
+        let mut table = QTable::default();
+        let params = QLearningParams::default();
+        let data = EngineData::default();
+        for _ in 0..200 {
+            play_qlearning_game(&mut table, Player::PlayerX, None, &params, true, &data).unwrap();
+        }
+        assert!(!table.is_empty());
+
+        // a winning move is learned from the final reward
+        let b = board::parse_board("XX./OO./...").unwrap();
+        let mut table = QTable::default();
+        table.update(&b, (0, 2), 1.0, 1.0);
+        let m = qlearning_ai(&b, Some(&table)).unwrap();
+        assert_eq!((0, 2), m.coord);
+
+        let mut play = |agent, opponent, learn| {
+            play_qlearning_game(&mut table, agent, opponent, &params, learn, &data)
+        };
+        let results = training::evaluate(&mut play, Engine::RandomMove, 4).unwrap();
+        assert_eq!(4, results.wins + results.draws + results.losses);
+
+        let schedule = Schedule {
+            games: 10,
+            opponent: None,
+            eval_opponent: Engine::RandomMove,
+            eval_games: 2,
+            eval_every: 5,
+        };
+        let mut evaluations = 0;
+        train_qtable(&mut table, &schedule, &params, |_, _| evaluations += 1).unwrap();
+        assert_eq!(2, evaluations);
+        let diverging = QLearningParams {
+            discount: f64::NAN,
+            ..params
+        };
+        assert!(train_qtable(&mut table, &schedule, &diverging, |_, _| {}).is_err());
+    }
+}
@@ -0,0 +1,119 @@
+use std::io::{self, IsTerminal};
+
+use crate::board::{self, Board, Move};
+
+/// How the boards are printed
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+#[non_exhaustive]
+pub enum Theme {
+    /// Plain characters, as written by `board::render_board`
+    #[default]
+    Ascii,
+    /// Box-drawing frame
+    Unicode,
+    /// Box-drawing frame, one color per player, last move and winning line highlighted
+    Color,
+}
+
+/// Return the theme with this name (ascii, unicode, color or auto), None if unknown
+pub fn theme_from_name(name: &str) -> Option<Theme> {
+    match name {
+        "ascii" => Some(Theme::Ascii),
+        "unicode" => Some(Theme::Unicode),
+        "color" => Some(Theme::Color),
+        "auto" => Some(auto_theme()),
+        _ => None,
+    }
+}
+
+/// Color when the standard output is a terminal, plain characters for files and pipes
+pub fn auto_theme() -> Theme {
+    if io::stdout().is_terminal() {
+        Theme::Color
+    } else {
+        Theme::Ascii
+    }
+}
+
+/// Render the board with its coordinates. `last_move` is only highlighted by the Color theme.
+pub fn render(board: &Board, theme: Theme, last_move: Option<Move>) -> String {
+    if theme == Theme::Ascii {
+        return board::render_board(board).unwrap();
+    }
+    let winning_line = board::winning_line(board);
+    let mut output = "   0   1   2\n".to_owned();
+    output += " ┌───┬───┬───┐\n";
+    for (y, line) in board.iter().enumerate() {
+        output += &format!("{}│", y);
+        for (x, c) in line.iter().enumerate() {
+            let content = format!(" {} ", c.unwrap_or(' '));
+            if theme == Theme::Color {
+                let highlighted = winning_line.is_some_and(|l| l.contains(&(y, x)));
+                output += &colorize(content, *c, highlighted, last_move == Some((y, x)));
+            } else {
+                output += &content;
+            }
+            output.push('│');
+        }
+        output.push('\n');
+        if y < 2 {
+            output += " ├───┼───┼───┤\n";
+        }
+    }
+    output += " └───┴───┴───┘\n";
+    output
+}
+
+/// Surround the content of a case with ANSI escape codes : bold red X, bold blue O, on green
+/// in the winning line or on grey for the last move
+fn colorize(content: String, case: Option<char>, winning: bool, last_move: bool) -> String {
+    let mut codes = Vec::new();
+    match case {
+        Some('X') => codes.extend(["1", "91"]),
+        Some(_) => codes.extend(["1", "94"]),
+        None => {}
+    }
+    if winning {
+        codes.push("102");
+    } else if last_move {
+        codes.push("100");
+    }
+    if codes.is_empty() {
+        return content;
+    }
+    format!("\u{1b}[{}m{}\u{1b}[0m", codes.join(";"), content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn init() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[test]
+    fn test_render() {
+        init();
+        let b = board::parse_board("X.O/.X./O..").unwrap();
+        assert_eq!(
+            board::render_board(&b).unwrap(),
+            render(&b, Theme::Ascii, None)
+        );
+        let expected = "   0   1   2\n".to_owned()
+            + " ┌───┬───┬───┐\n"
+            + "0│ X │   │ O │\n"
+            + " ├───┼───┼───┤\n"
+            + "1│   │ X │   │\n"
+            + " ├───┼───┼───┤\n"
+            + "2│ O │   │   │\n"
+            + " └───┴───┴───┘\n";
+        assert_eq!(expected, render(&b, Theme::Unicode, Some((1, 1))));
+
+        let colored = render(&b, Theme::Color, Some((1, 1)));
+        assert!(colored.contains('\u{1b}'));
+        assert_eq!(expected.lines().count(), colored.lines().count());
+        assert_eq!(Some(Theme::Unicode), theme_from_name("unicode"));
+        assert_eq!(None, theme_from_name("fancy"));
+    }
+}
@@ -0,0 +1,215 @@
+use std::fmt;
+
+use anyhow::{anyhow, Result};
+use log::info;
+
+use crate::ai::{self, EngineMove};
+use crate::board::{self, Board, Move};
+use crate::game;
+use crate::player::Player;
+
+const CENTER: Move = (1, 1);
+const CORNERS: [Move; 4] = [(0, 0), (0, 2), (2, 0), (2, 2)];
+const SIDES: [Move; 4] = [(0, 1), (1, 0), (1, 2), (2, 1)];
+
+/// Rules of Newell and Simon's tic-tac-toe program, by priority
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Rule {
+    Win,
+    Block,
+    Fork,
+    BlockFork,
+    Center,
+    OppositeCorner,
+    EmptyCorner,
+    EmptySide,
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let rule = match self {
+            Rule::Win => "win",
+            Rule::Block => "block the opponent win",
+            Rule::Fork => "create a fork",
+            Rule::BlockFork => "block the opponent fork",
+            Rule::Center => "take the center",
+            Rule::OppositeCorner => "take the corner opposite to the opponent",
+            Rule::EmptyCorner => "take an empty corner",
+            Rule::EmptySide => "take an empty side",
+        };
+        write!(f, "{}", rule)
+    }
+}
+
+/// Return the move of the first rule that applies, and the rule. Perfect play without
+/// search : the engine never loses.
+pub fn rule_move(board: &Board, player: &Player) -> Option<(Move, Rule)> {
+    let legal_moves = ai::find_all_legal_moves(board);
+    if legal_moves.is_empty() {
+        return None;
+    }
+    let opponent = game::switch_player(player);
+
+    if let Some(m) = ai::find_a_winning_move(&legal_moves, board, player) {
+        return Some((m, Rule::Win));
+    }
+    if let Some(m) = ai::find_a_blocking_move(&legal_moves, board, player) {
+        return Some((m, Rule::Block));
+    }
+    if let Some(m) = fork_moves(&legal_moves, board, player).first() {
+        return Some((*m, Rule::Fork));
+    }
+    if let Some(m) = block_fork_move(&legal_moves, board, player, &opponent) {
+        return Some((m, Rule::BlockFork));
+    }
+    if board[CENTER.0][CENTER.1].is_none() {
+        return Some((CENTER, Rule::Center));
+    }
+    let opponent_case = match opponent {
+        Player::PlayerX => Some('X'),
+        Player::PlayerO => Some('O'),
+    };
+    for (y, x) in CORNERS {
+        if board[y][x] == opponent_case && board[2 - y][2 - x].is_none() {
+            return Some(((2 - y, 2 - x), Rule::OppositeCorner));
+        }
+    }
+    if let Some(m) = CORNERS.into_iter().find(|m| legal_moves.contains(m)) {
+        return Some((m, Rule::EmptyCorner));
+    }
+    SIDES
+        .into_iter()
+        .find(|m| legal_moves.contains(m))
+        .map(|m| (m, Rule::EmptySide))
+}
+
+/// Play the move of the first rule that applies
+pub fn rules_ai(board: &Board, player: &Player) -> Result<EngineMove> {
+    match rule_move(board, player) {
+        Some((m, rule)) => {
+            info!("Rule applied : {}", rule);
+            Ok(m.into())
+        }
+        None => Err(anyhow!("no legal move available")),
+    }
+}
+
+/// Cases where `player` would win at once
+fn winning_cases(board: &Board, player: &Player) -> Vec<Move> {
+    ai::find_all_legal_moves(board)
+        .into_iter()
+        .filter(|m| {
+            let new_board = board::make_move(board, *m, player).unwrap();
+            board::is_move_win(&new_board).is_some()
+        })
+        .collect()
+}
+
+/// Moves after which `player` has two ways to win
+fn fork_moves(legal_moves: &[Move], board: &Board, player: &Player) -> Vec<Move> {
+    legal_moves
+        .iter()
+        .filter(|m| {
+            let new_board = board::make_move(board, **m, player).unwrap();
+            winning_cases(&new_board, player).len() >= 2
+        })
+        .copied()
+        .collect()
+}
+
+/// Block the only fork of the opponent. With several forks, make two in a row to force the
+/// opponent to defend on a case that doesn't give it a fork, on one of its fork cases if
+/// possible.
+fn block_fork_move(
+    legal_moves: &[Move],
+    board: &Board,
+    player: &Player,
+    opponent: &Player,
+) -> Option<Move> {
+    let opponent_forks = fork_moves(legal_moves, board, opponent);
+    match opponent_forks.len() {
+        0 => return None,
+        1 => return Some(opponent_forks[0]),
+        _ => {}
+    }
+    let forcing_moves: Vec<Move> = legal_moves
+        .iter()
+        .filter(|m| {
+            let new_board = board::make_move(board, **m, player).unwrap();
+            match winning_cases(&new_board, player)[..] {
+                [defense] => {
+                    let defended = board::make_move(&new_board, defense, opponent).unwrap();
+                    winning_cases(&defended, opponent).len() < 2
+                }
+                _ => false,
+            }
+        })
+        .copied()
+        .collect();
+    forcing_moves
+        .iter()
+        .find(|m| opponent_forks.contains(m))
+        .or(forcing_moves.first())
+        .or(opponent_forks.first())
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn init() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    /// Check every game where the rules play `rules_player` against any move
+    fn never_loses(board: &Board, rules_player: &Player, active_player: &Player) -> bool {
+        if board::is_move_win(board).is_some() {
+            return board::is_move_win(board) == Some(*rules_player);
+        }
+        if board::is_board_full(board) {
+            return true;
+        }
+        let next_player = game::switch_player(active_player);
+        if active_player == rules_player {
+            let (m, _) = rule_move(board, active_player).unwrap();
+            let new_board = board::make_move(board, m, active_player).unwrap();
+            return never_loses(&new_board, rules_player, &next_player);
+        }
+        ai::find_all_legal_moves(board).into_iter().all(|m| {
+            let new_board = board::make_move(board, m, active_player).unwrap();
+            never_loses(&new_board, rules_player, &next_player)
+        })
+    }
+
+    #[test]
+    fn test_rule_move() {
+        init();
+        let b = board::parse_board("XX./OO./...").unwrap();
+        assert_eq!(Some(((0, 2), Rule::Win)), rule_move(&b, &Player::PlayerX));
+        let b = board::parse_board("XX./O../...").unwrap();
+        assert_eq!(Some(((0, 2), Rule::Block)), rule_move(&b, &Player::PlayerO));
+        let b = board::parse_board("X../.O./..X").unwrap();
+        let (m, rule) = rule_move(&b, &Player::PlayerO).unwrap();
+        assert_eq!(Rule::BlockFork, rule);
+        assert!(SIDES.contains(&m));
+        let b = board::generate_new_board();
+        assert_eq!(
+            Some((CENTER, Rule::Center)),
+            rule_move(&b, &Player::PlayerX)
+        );
+        let b = board::parse_board(".../.X./..O").unwrap();
+        assert_eq!(
+            Some(((0, 0), Rule::OppositeCorner)),
+            rule_move(&b, &Player::PlayerX)
+        );
+    }
+
+    #[test]
+    fn test_rules_never_lose() {
+        init();
+        let b = board::generate_new_board();
+        assert!(never_loses(&b, &Player::PlayerX, &Player::PlayerX));
+        assert!(never_loses(&b, &Player::PlayerO, &Player::PlayerX));
+    }
+}
@@ -0,0 +1,157 @@
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
+
+use crate::ai;
+use crate::board::{self, Board};
+
+/// Number of positions by game-theoretic value
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct ValueCount {
+    /// won by X
+    pub x_wins: usize,
+    /// drawn
+    pub draws: usize,
+    /// won by O
+    pub o_wins: usize,
+}
+
+impl ValueCount {
+    fn add(&mut self, score: i8) {
+        match score {
+            s if s > 0 => self.x_wins += 1,
+            0 => self.draws += 1,
+            _ => self.o_wins += 1,
+        }
+    }
+}
+
+/// Counts of the reachable positions and of their values
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct SolverReport {
+    /// reachable positions, the empty board included
+    pub positions: usize,
+    /// positions where the game is over
+    pub terminal: ValueCount,
+    /// reachable positions different modulo the 8 symmetries of the board
+    pub unique_positions: usize,
+    /// values of all the reachable positions with perfect play
+    pub values: ValueCount,
+    /// values of the unique positions with perfect play
+    pub unique_values: ValueCount,
+    /// minimax score of the empty board
+    pub start_value: i8,
+}
+
+/// Return every position reachable from the empty board
+pub fn reachable_positions() -> HashSet<Board> {
+    let mut positions: HashSet<Board> = HashSet::new();
+    let mut to_visit = vec![board::generate_new_board()];
+    while let Some(board) = to_visit.pop() {
+        if !positions.insert(board) {
+            continue;
+        }
+        if board::is_move_win(&board).is_some() {
+            continue;
+        }
+        let player = board::player_to_move(&board);
+        for m in ai::find_all_legal_moves(&board) {
+            to_visit.push(board::make_move(&board, m, &player).unwrap());
+        }
+    }
+    positions
+}
+
+/// Enumerate every position reachable from the empty board and solve it
+pub fn solve() -> SolverReport {
+    let positions = reachable_positions();
+    let mut cache: HashMap<u64, i8> = HashMap::new();
+    let mut unique: HashSet<Board> = HashSet::new();
+    let mut report = SolverReport {
+        positions: positions.len(),
+        ..Default::default()
+    };
+    for board in &positions {
+        let value = ai::minimax_value(board, &mut cache);
+        if board::is_move_win(board).is_some() || board::is_board_full(board) {
+            report.terminal.add(value);
+        }
+        report.values.add(value);
+        if unique.insert(board::canonical_board(board).0) {
+            report.unique_values.add(value);
+        }
+    }
+    report.unique_positions = unique.len();
+    report.start_value = ai::minimax_value(&board::generate_new_board(), &mut cache);
+    report
+}
+
+/// Print the value of every unique position
+pub fn print_unique_positions(out: &mut impl Write) -> io::Result<()> {
+    let mut cache: HashMap<u64, i8> = HashMap::new();
+    let mut unique: Vec<Board> = reachable_positions()
+        .iter()
+        .map(|b| board::canonical_board(b).0)
+        .collect::<HashSet<Board>>()
+        .into_iter()
+        .collect();
+    unique.sort();
+    for board in unique {
+        let value = ai::minimax_value(&board, &mut cache);
+        writeln!(
+            out,
+            "{}value : {}\n",
+            board::render_board(&board).unwrap(),
+            value
+        )?;
+    }
+    Ok(())
+}
+
+/// Print the counts of the report, one per line
+pub fn print_report(out: &mut impl Write, report: &SolverReport) -> io::Result<()> {
+    writeln!(out, "Reachable positions : {}", report.positions)?;
+    writeln!(
+        out,
+        "Terminal positions : {} X wins, {} O wins, {} draws",
+        report.terminal.x_wins, report.terminal.o_wins, report.terminal.draws
+    )?;
+    writeln!(
+        out,
+        "Unique positions modulo symmetry : {}",
+        report.unique_positions
+    )?;
+    writeln!(
+        out,
+        "Values of all positions : {} X wins, {} O wins, {} draws",
+        report.values.x_wins, report.values.o_wins, report.values.draws
+    )?;
+    writeln!(
+        out,
+        "Values of unique positions : {} X wins, {} O wins, {} draws",
+        report.unique_values.x_wins, report.unique_values.o_wins, report.unique_values.draws
+    )?;
+    writeln!(out, "Value of the empty board : {}", report.start_value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn init() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[test]
+    fn test_solve() {
+        init();
+        let report = solve();
+        assert_eq!(5478, report.positions);
+        assert_eq!(626, report.terminal.x_wins);
+        assert_eq!(316, report.terminal.o_wins);
+        assert_eq!(16, report.terminal.draws);
+        assert_eq!(765, report.unique_positions);
+        assert_eq!(0, report.start_value);
+        let total = report.values.x_wins + report.values.o_wins + report.values.draws;
+        assert_eq!(report.positions, total);
+    }
+}
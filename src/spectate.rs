@@ -0,0 +1,282 @@
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::Result;
+use log::info;
+use serde_json::{json, Value};
+use tungstenite::{Error, Message, WebSocket};
+
+use crate::ai;
+use crate::board::{self, Board};
+use crate::game::{self, Engine, GameRecord};
+use crate::observer::{EndReason, GameObserver, MoveEvent};
+use crate::player::Player;
+
+/// Messages waiting for a spectator, who is dropped when they fill up
+const QUEUE_SIZE: usize = 64;
+/// Time given to a client to complete the WebSocket handshake
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Time a spectator waits for a message before reading its socket
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// Time given to a spectator to receive a message
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Default)]
+struct Spectators {
+    /// queues of the threads writing to the spectators
+    queues: Vec<SyncSender<String>>,
+    /// messages of the current game, sent to the spectators joining during the game
+    history: Vec<String>,
+}
+
+/// Stream the games as JSON messages to every WebSocket client connected on its address.
+/// Each message has a "type" : "start" (engines), "move" (player, engine, move, board,
+/// clocks in milliseconds and minimax eval from X point of view) or "end" (winner and
+/// reason), and also gives the current board and the score of the match.
+/// Boards, moves, players and engines have the JSON shape of the `serde` feature.
+pub struct Broadcaster {
+    address: SocketAddr,
+    spectators: Arc<Mutex<Spectators>>,
+    /// time left on the clock of X and of O
+    clocks: (Option<Duration>, Option<Duration>),
+    /// wins of X, wins of O and draws
+    score: (usize, usize, usize),
+    cache: HashMap<u64, i8>,
+}
+
+impl Broadcaster {
+    /// Accept spectators on `address` in the background
+    pub fn bind(address: &str) -> Result<Broadcaster> {
+        let listener = TcpListener::bind(address)?;
+        let address = listener.local_addr()?;
+        info!("Spectators accepted on {}", address);
+        let spectators: Arc<Mutex<Spectators>> = Arc::default();
+        let shared = Arc::clone(&spectators);
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let shared = Arc::clone(&shared);
+                thread::spawn(move || {
+                    if let Err(e) = spectate(stream, &shared) {
+                        info!("Spectator left : {}", e);
+                    }
+                });
+            }
+        });
+        Ok(Broadcaster {
+            address,
+            spectators,
+            clocks: (None, None),
+            score: (0, 0, 0),
+            cache: HashMap::new(),
+        })
+    }
+
+    /// Address the spectators connect to
+    pub fn local_addr(&self) -> SocketAddr {
+        self.address
+    }
+
+    fn broadcast(&mut self, mut message: Value, board: &Board) {
+        message["board"] = json!(board);
+        message["score"] = json!({ "x": self.score.0, "o": self.score.1, "draw": self.score.2 });
+        let text = message.to_string();
+        let mut spectators = self.spectators.lock().unwrap();
+        spectators.history.push(text.clone());
+        // disconnected spectators, and the ones too slow to follow the game, are dropped
+        spectators
+            .queues
+            .retain(|queue| queue.try_send(text.clone()).is_ok());
+    }
+}
+
+/// Complete the handshake of a spectator, send it the messages of the current game then the
+/// ones of its queue, answering its pings, until it leaves or is dropped
+fn spectate(stream: TcpStream, spectators: &Mutex<Spectators>) -> Result<()> {
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    let mut socket = tungstenite::accept(stream)?;
+    socket.get_ref().set_read_timeout(Some(POLL_INTERVAL))?;
+    let (sender, queue) = mpsc::sync_channel(QUEUE_SIZE);
+    let history = {
+        let mut spectators = spectators.lock().unwrap();
+        spectators.queues.push(sender);
+        spectators.history.clone()
+    };
+    for message in history {
+        socket.send(Message::text(message))?;
+    }
+    forward(&mut socket, &queue)
+}
+
+fn forward(socket: &mut WebSocket<TcpStream>, queue: &Receiver<String>) -> Result<()> {
+    loop {
+        match queue.recv_timeout(POLL_INTERVAL) {
+            Ok(message) => socket.send(Message::text(message))?,
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => {
+                socket.close(None)?;
+                return Ok(());
+            }
+        }
+        // pings are answered and closes acknowledged by the next flush
+        match socket.read() {
+            Ok(_) => {}
+            Err(Error::Io(e))
+                if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::TimedOut => {}
+            Err(Error::ConnectionClosed) => return Ok(()),
+            Err(e) => return Err(e.into()),
+        }
+        socket.flush()?;
+    }
+}
+
+fn millis(duration: Option<Duration>) -> Value {
+    json!(duration.map(|d| d.as_millis() as u64))
+}
+
+impl GameObserver for Broadcaster {
+    fn on_game_started(&mut self, engine_x: Engine, engine_o: Engine, board: &Board) {
+        self.clocks = (None, None);
+        self.spectators.lock().unwrap().history.clear();
+        let message = json!({
+            "type": "start",
+            "x": engine_x,
+            "o": engine_o,
+        });
+        self.broadcast(message, board);
+    }
+
+    fn on_move_played(&mut self, event: &MoveEvent) {
+        match event.player {
+            Player::PlayerX => self.clocks.0 = event.remaining,
+            Player::PlayerO => self.clocks.1 = event.remaining,
+        }
+        let eval = ai::minimax_value(&event.board, &mut self.cache);
+        let message = json!({
+            "type": "move",
+            "player": event.player,
+            "engine": event.engine,
+            "move": event.engine_move.coord,
+            "elapsed_ms": event.elapsed.as_millis() as u64,
+            "clocks": { "x": millis(self.clocks.0), "o": millis(self.clocks.1) },
+            "eval": eval,
+        });
+        self.broadcast(message, &event.board);
+    }
+
+    fn on_game_ended(&mut self, record: &GameRecord, reason: EndReason) {
+        match record.winner {
+            Some(Player::PlayerX) => self.score.0 += 1,
+            Some(Player::PlayerO) => self.score.1 += 1,
+            None => self.score.2 += 1,
+        }
+        let mut board = board::generate_new_board();
+        let mut player = Player::PlayerX;
+        for m in &record.moves {
+            board = board::make_move(&board, *m, &player).unwrap();
+            player = game::switch_player(&player);
+        }
+        let message = json!({
+            "type": "end",
+            "winner": record.winner,
+            "reason": format!("{:?}", reason).to_lowercase(),
+        });
+        self.broadcast(message, &board);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::EngineMove;
+    use std::time::Instant;
+
+    fn init() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    fn read_json(socket: &mut WebSocket<tungstenite::stream::MaybeTlsStream<TcpStream>>) -> Value {
+        let message = socket.read().unwrap();
+        serde_json::from_str(message.to_text().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_broadcaster() {
+        init();
+        let mut broadcaster = Broadcaster::bind("127.0.0.1:0").unwrap();
+        let address = broadcaster.local_addr();
+
+        let b = board::generate_new_board();
+        broadcaster.on_game_started(Engine::Rules, Engine::RandomMove, &b);
+        let (mut socket, _) = tungstenite::connect(format!("ws://{}", address)).unwrap();
+        let start = read_json(&mut socket);
+        assert_eq!(json!("start"), start["type"]);
+        assert_eq!(json!("Rules"), start["x"]);
+
+        // the spectator is registered by a background thread
+        let spectators = |b: &Broadcaster| b.spectators.lock().unwrap().queues.len();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while spectators(&broadcaster) == 0 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(1, spectators(&broadcaster));
+        let b = board::make_move(&b, (1, 1), &Player::PlayerX).unwrap();
+        broadcaster.on_move_played(&MoveEvent {
+            player: Player::PlayerX,
+            engine: Engine::Rules,
+            engine_move: EngineMove::from((1, 1)),
+            board: b,
+            elapsed: Duration::from_millis(3),
+            remaining: Some(Duration::from_secs(2)),
+        });
+        let played = read_json(&mut socket);
+        assert_eq!(json!("move"), played["type"]);
+        assert_eq!(json!([null, "X", null]), played["board"][1]);
+        assert_eq!(json!([1, 1]), played["move"]);
+        assert_eq!(json!("X"), played["player"]);
+        assert_eq!(json!({"x": 2000, "o": null}), played["clocks"]);
+        assert_eq!(json!(0), played["eval"]);
+
+        let record = GameRecord {
+            moves: vec![(1, 1)],
+            winner: Some(Player::PlayerX),
+        };
+        broadcaster.on_game_ended(&record, EndReason::Time);
+        let end = read_json(&mut socket);
+        assert_eq!(json!("X"), end["winner"]);
+        assert_eq!(json!("time"), end["reason"]);
+        assert_eq!(json!({"x": 1, "o": 0, "draw": 0}), end["score"]);
+    }
+
+    #[test]
+    fn test_spectator_leaving() {
+        init();
+        let mut broadcaster = Broadcaster::bind("127.0.0.1:0").unwrap();
+        let address = broadcaster.local_addr();
+        let b = board::generate_new_board();
+        broadcaster.on_game_started(Engine::Rules, Engine::Rules, &b);
+        let (mut socket, _) = tungstenite::connect(format!("ws://{}", address)).unwrap();
+        assert_eq!(json!("start"), read_json(&mut socket)["type"]);
+
+        socket.send(Message::Ping("ping".into())).unwrap();
+        assert_eq!(Message::Pong("ping".into()), socket.read().unwrap());
+        socket.close(None).unwrap();
+        while socket.read().is_ok() {}
+
+        // the queue of the spectator is closed once it left, and dropped by the next message
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            broadcaster.on_game_started(Engine::Rules, Engine::Rules, &b);
+            if broadcaster.spectators.lock().unwrap().queues.is_empty() {
+                break;
+            }
+            assert!(Instant::now() < deadline);
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
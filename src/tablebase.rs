@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{bail, Result};
+
+use crate::ai::{self, EngineMove, SearchLimits, TieBreak};
+use crate::board::{self, Board, Move};
+use crate::player::Player;
+use crate::solver;
+
+const MAGIC: &[u8; 4] = b"TTTB";
+const VERSION: u8 = 1;
+
+/// Best moves and value of canonical positions (see `board::canonical_board`)
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Tablebase {
+    /// position encoded by `encode_board` -> (minimax score, best moves as a bit per case)
+    entries: HashMap<u16, (i8, u16)>,
+}
+
+impl Tablebase {
+    /// Solve every reachable position where the game is not over, or only the positions
+    /// with at most `max_depth` moves played (an opening book)
+    pub fn generate(max_depth: Option<usize>) -> Tablebase {
+        let mut cache: HashMap<u64, i8> = HashMap::new();
+        let mut entries = HashMap::new();
+        for b in solver::reachable_positions() {
+            let legal_moves = ai::find_all_legal_moves(&b);
+            let depth = 9 - legal_moves.len();
+            if board::is_move_win(&b).is_some()
+                || legal_moves.is_empty()
+                || max_depth.is_some_and(|max| depth > max)
+            {
+                continue;
+            }
+            let (canonical, _) = board::canonical_board(&b);
+            let key = encode_board(&canonical);
+            if entries.contains_key(&key) {
+                continue;
+            }
+            let player = board::player_to_move(&canonical);
+            let scores = ai::minimax_scores(&canonical, &player, &mut cache);
+            let best = match player {
+                Player::PlayerX => scores.iter().map(|s| s.0).max().unwrap(),
+                Player::PlayerO => scores.iter().map(|s| s.0).min().unwrap(),
+            };
+            let best_moves = scores
+                .iter()
+                .filter(|s| s.0 == best)
+                .fold(0u16, |bits, s| bits | 1 << (s.1 .0 * 3 + s.1 .1));
+            entries.insert(key, (best, best_moves));
+        }
+        Tablebase { entries }
+    }
+
+    /// Number of positions
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the tablebase has no position
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Return the minimax score and the best moves of a position, if in the table
+    pub fn lookup(&self, board: &Board) -> Option<(i8, Vec<Move>)> {
+        let (canonical, symmetry) = board::canonical_board(board);
+        let (score, bits) = self.entries.get(&encode_board(&canonical))?;
+        let inverse = board::inverse_symmetry(symmetry);
+        let moves = (0..9)
+            .filter(|i| bits & (1 << i) != 0)
+            .map(|i| board::transform_move((i / 3, i % 3), inverse))
+            .collect();
+        Some((*score, moves))
+    }
+
+    /// Binary format, little endian : "TTTB", version (u8), number of entries (u32), then
+    /// for each entry the position (u16, base 3 : 0 empty, 1 X, 2 O, first case being the
+    /// least significant digit), the score (i8) and the best moves (u16, bit y*3+x).
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut keys: Vec<&u16> = self.entries.keys().collect();
+        keys.sort();
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(VERSION);
+        bytes.extend((keys.len() as u32).to_le_bytes());
+        for key in keys {
+            let (score, moves) = self.entries[key];
+            bytes.extend(key.to_le_bytes());
+            bytes.extend(score.to_le_bytes());
+            bytes.extend(moves.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Read a tablebase written by `to_bytes`. Fail if a record is not a reachable position
+    /// with at least one best move, every one on an empty case.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Tablebase> {
+        if bytes.len() < 9 || &bytes[0..4] != MAGIC {
+            bail!("Not a tablebase file");
+        }
+        if bytes[4] != VERSION {
+            bail!("Unsupported tablebase version {}", bytes[4]);
+        }
+        let count = u32::from_le_bytes(bytes[5..9].try_into()?) as usize;
+        let records = &bytes[9..];
+        if records.len() != count * 5 {
+            bail!("Tablebase file truncated");
+        }
+        let mut entries = HashMap::new();
+        for r in records.chunks(5) {
+            let key = u16::from_le_bytes([r[0], r[1]]);
+            let score = r[2] as i8;
+            let moves = u16::from_le_bytes([r[3], r[4]]);
+            check_record(key, moves)?;
+            entries.insert(key, (score, moves));
+        }
+        Ok(Tablebase { entries })
+    }
+
+    /// Write the tablebase to a file in the format of `to_bytes`
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.to_bytes())?;
+        Ok(())
+    }
+
+    /// Read a tablebase written by `save`
+    pub fn load(path: &Path) -> Result<Tablebase> {
+        Tablebase::from_bytes(&fs::read(path)?)
+    }
+}
+
+fn encode_board(board: &Board) -> u16 {
+    board.iter().flatten().rev().fold(0, |code, c| {
+        code * 3
+            + match c {
+                None => 0,
+                Some('X') => 1,
+                Some(_) => 2,
+            }
+    })
+}
+
+/// Inverse of `encode_board`, None if the code is not a position
+fn decode_board(code: u16) -> Option<Board> {
+    if code >= 3u16.pow(9) {
+        return None;
+    }
+    let mut board = board::generate_new_board();
+    let mut code = code;
+    for case in board.iter_mut().flatten() {
+        *case = match code % 3 {
+            0 => None,
+            1 => Some('X'),
+            _ => Some('O'),
+        };
+        code /= 3;
+    }
+    Some(board)
+}
+
+/// Check that a record is a reachable position whose best moves are empty cases
+fn check_record(key: u16, moves: u16) -> Result<()> {
+    let Some(b) = decode_board(key) else {
+        bail!("Tablebase position {} out of range", key);
+    };
+    board::check_reachable(&b)?;
+    if moves == 0 || moves >= 1 << 9 {
+        bail!(
+            "Tablebase moves {:#b} of {} invalid",
+            moves,
+            board::board_to_string(&b)
+        );
+    }
+    for i in (0..9).filter(|i| moves & (1 << i) != 0) {
+        if !board::is_valid_move(&b, (i / 3, i % 3)) {
+            bail!(
+                "Tablebase move {} of {} is not an empty case",
+                board::format_move((i / 3, i % 3)),
+                board::board_to_string(&b)
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Play instantly the first best move of the table, or search with minimax when the
+/// position is missing
+pub fn tablebase_ai(
+    board: &Board,
+    player: &Player,
+    tablebase: Option<&Tablebase>,
+    cache: &mut HashMap<u64, i8>,
+    limits: &SearchLimits,
+) -> Result<EngineMove> {
+    let best = tablebase
+        .and_then(|t| t.lookup(board))
+        .and_then(|(score, moves)| Some((score, moves.into_iter().min()?)));
+    if let Some((score, m)) = best {
+        return Ok(EngineMove {
+            coord: m,
+            score: Some(score),
+            pv: vec![m],
+        });
+    }
+    log::debug!("position not in tablebase, searching");
+    ai::minimax_algo_ai(board, player, cache, limits, TieBreak::First)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn init() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[test]
+    fn test_encode_board() {
+        init();
+        assert_eq!(0, encode_board(&board::generate_new_board()));
+        let board = board::parse_board("XO./.../...").unwrap();
+        assert_eq!(1 + 2 * 3, encode_board(&board));
+        assert_eq!(Some(board), decode_board(1 + 2 * 3));
+        assert_eq!(None, decode_board(3u16.pow(9)));
+    }
+
+    #[test]
+    fn test_tablebase() {
+        init();
+        let tablebase = Tablebase::generate(None);
+        assert_eq!(
+            tablebase,
+            Tablebase::from_bytes(&tablebase.to_bytes()).unwrap()
+        );
+        assert!(Tablebase::from_bytes(b"TTTB").is_err());
+        let record = |key: u16, moves: u16| {
+            let mut bytes = b"TTTB\x01\x01\x00\x00\x00".to_vec();
+            bytes.extend(key.to_le_bytes());
+            bytes.push(0);
+            bytes.extend(moves.to_le_bytes());
+            bytes
+        };
+        assert!(Tablebase::from_bytes(&record(0, 1 << 4)).is_ok());
+        // no move, move out of the board, taken case, unreachable and out of range positions
+        for (key, moves) in [(0, 0), (0, 1 << 9), (1, 1), (2, 1 << 4), (20000, 1)] {
+            assert!(Tablebase::from_bytes(&record(key, moves)).is_err());
+        }
+
+        let board = board::parse_board("X.O/.X./O..").unwrap();
+        assert_eq!(Some((14, vec![(2, 2)])), tablebase.lookup(&board));
+        let rotated = board::transform_board(&board, 1);
+        let (_, moves) = tablebase.lookup(&rotated).unwrap();
+        assert_eq!(vec![board::transform_move((2, 2), 1)], moves);
+
+        let (score, moves) = tablebase.lookup(&board::generate_new_board()).unwrap();
+        assert_eq!(0, score);
+        assert_eq!(9, moves.len());
+
+        let book = Tablebase::generate(Some(2));
+        assert_eq!(1 + 3 + 12, book.len());
+        assert!(book.lookup(&board).is_none());
+    }
+
+    #[test]
+    fn test_tablebase_ai() {
+        init();
+        let book = Tablebase::generate(Some(1));
+        let board = board::parse_board("X.O/.X./O..").unwrap();
+        let mut cache: HashMap<u64, i8> = HashMap::new();
+        let limits = SearchLimits::default();
+        let m = tablebase_ai(&board, &Player::PlayerX, Some(&book), &mut cache, &limits);
+        assert_eq!((2, 2), m.unwrap().coord);
+        let m = tablebase_ai(&board, &Player::PlayerX, None, &mut cache, &limits);
+        assert_eq!((2, 2), m.unwrap().coord);
+    }
+}
@@ -0,0 +1,164 @@
+use std::path::Path;
+
+use anyhow::{bail, Result};
+
+use crate::game::Engine;
+use crate::player::Player;
+
+/// Results of a learning engine against an opponent
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct TrainingResults {
+    /// games won by the learning engine
+    pub wins: usize,
+    /// games drawn
+    pub draws: usize,
+    /// games lost by the learning engine
+    pub losses: usize,
+}
+
+/// Training games of a learning engine and their evaluations
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Schedule {
+    /// number of training games
+    pub games: usize,
+    /// engine trained against, the learning engine itself if None
+    pub opponent: Option<Engine>,
+    /// engine playing the evaluation games
+    pub eval_opponent: Engine,
+    /// number of evaluation games
+    pub eval_games: usize,
+    /// number of training games between evaluations
+    pub eval_every: usize,
+}
+
+/// Side played by the learning engine in the game `i`, X and O in turn
+fn agent(i: usize) -> Player {
+    if i.is_multiple_of(2) {
+        Player::PlayerX
+    } else {
+        Player::PlayerO
+    }
+}
+
+/// Play `games` games against `opponent` without learning, switching sides. `play` plays a
+/// game of the learning engine as its first argument against the engine of the second one
+/// (itself if None), learning from it if the third one is set, and returns the winner.
+pub fn evaluate(
+    play: &mut impl FnMut(Player, Option<Engine>, bool) -> Result<Option<Player>>,
+    opponent: Engine,
+    games: usize,
+) -> Result<TrainingResults> {
+    let mut results = TrainingResults::default();
+    for i in 0..games {
+        let agent = agent(i);
+        match play(agent, Some(opponent), false)? {
+            Some(w) if w == agent => results.wins += 1,
+            Some(_) => results.losses += 1,
+            None => results.draws += 1,
+        }
+    }
+    Ok(results)
+}
+
+/// Play the training games of `schedule` with `play`, as described by `evaluate`, switching
+/// sides. `on_evaluation` is called with the number of games played and the results of the
+/// evaluation games, every `eval_every` games and after the last one.
+pub fn train(
+    schedule: &Schedule,
+    mut play: impl FnMut(Player, Option<Engine>, bool) -> Result<Option<Player>>,
+    mut on_evaluation: impl FnMut(usize, &TrainingResults),
+) -> Result<()> {
+    if schedule.opponent == Some(Engine::Human) || schedule.eval_opponent == Engine::Human {
+        bail!("Human can not be a training or evaluation opponent");
+    }
+    if schedule.eval_every == 0 {
+        bail!("the number of games between evaluations must be at least 1");
+    }
+    for i in 0..schedule.games {
+        play(agent(i), schedule.opponent, true)?;
+        if (i + 1).is_multiple_of(schedule.eval_every) || i + 1 == schedule.games {
+            let results = evaluate(&mut play, schedule.eval_opponent, schedule.eval_games)?;
+            on_evaluation(i + 1, &results);
+        }
+    }
+    Ok(())
+}
+
+/// What a learning engine learned, saved between runs
+pub trait Model: Sized {
+    /// Read a model written by `save`
+    fn load(path: &Path) -> Result<Self>;
+
+    /// Write the model to a file
+    fn save(&self, path: &Path) -> Result<()>;
+
+    /// What is saved, like "124 positions"
+    fn summary(&self) -> String;
+}
+
+/// Load the model of `input`, or create it with `new`, train it and save it to `output`.
+/// Return the summary of the saved model.
+pub fn train_and_save<M: Model>(
+    input: Option<&Path>,
+    output: &Path,
+    new: impl FnOnce() -> M,
+    train: impl FnOnce(&mut M) -> Result<()>,
+) -> Result<String> {
+    let mut model = match input {
+        Some(path) => M::load(path)?,
+        None => new(),
+    };
+    train(&mut model)?;
+    model.save(output)?;
+    Ok(model.summary())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn init() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[test]
+    fn test_train() {
+        init();
+        let schedule = Schedule {
+            games: 5,
+            opponent: None,
+            eval_opponent: Engine::RandomMove,
+            eval_games: 4,
+            eval_every: 2,
+        };
+        let mut trained = 0;
+        let mut evaluations = Vec::new();
+        // the agent wins every game
+        let play = |agent: Player, _: Option<Engine>, learn: bool| {
+            trained += usize::from(learn);
+            Ok(Some(agent))
+        };
+        train(&schedule, play, |games, results| {
+            evaluations.push((games, *results))
+        })
+        .unwrap();
+        assert_eq!(5, trained);
+        let results = TrainingResults {
+            wins: 4,
+            draws: 0,
+            losses: 0,
+        };
+        assert_eq!(vec![(2, results), (4, results), (5, results)], evaluations);
+
+        let no_evaluation = Schedule {
+            eval_every: 0,
+            ..schedule
+        };
+        assert!(train(&no_evaluation, |_, _, _| Ok(None), |_, _| {}).is_err());
+        let human = Schedule {
+            opponent: Some(Engine::Human),
+            ..schedule
+        };
+        assert!(train(&human, |_, _, _| Ok(None), |_, _| {}).is_err());
+    }
+}
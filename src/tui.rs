@@ -0,0 +1,459 @@
+use std::io::{self, Stdout, Write};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, bail, Result};
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{
+    self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind, KeyModifiers,
+    MouseButton, MouseEventKind,
+};
+use crossterm::style::{Print, PrintStyledContent, Stylize};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+
+use log::error;
+
+use crate::board::{self, Board, Move};
+use crate::clock::TimeControl;
+use crate::game::{self, Engine, EngineData, GameRecord};
+use crate::observer::{EndReason, GameObserver, MoveEvent};
+use crate::player::Player;
+
+/// Screen position of the top left case
+const GRID_TOP: u16 = 2;
+const GRID_LEFT: u16 = 2;
+/// Width of a case and its separator
+const CASE_WIDTH: u16 = 4;
+/// Pause after an engine move, so that engine games can be followed
+const ENGINE_DELAY: Duration = Duration::from_millis(400);
+/// Pause at the end of a game between two engines
+const GAME_END_DELAY: Duration = Duration::from_secs(2);
+
+/// Raw mode and alternate screen, restored when dropped
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn new() -> Result<TerminalGuard> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture, Hide)?;
+        Ok(TerminalGuard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = execute!(
+            io::stdout(),
+            Show,
+            DisableMouseCapture,
+            LeaveAlternateScreen
+        );
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// What the user asked while waiting for a key
+enum Input {
+    Play(Move),
+    Quit,
+    Nothing,
+}
+
+/// Observer drawing the games and giving the moves of the human players
+struct Tui {
+    out: Stdout,
+    engines: (Engine, Engine),
+    board: Board,
+    player: Player,
+    last_move: Option<Move>,
+    cursor: Move,
+    games: usize,
+    game: usize,
+    /// wins of X, wins of O and draws
+    score: (usize, usize, usize),
+    time_control: TimeControl,
+    /// time left on the clock of X and of O
+    clocks: (Option<Duration>, Option<Duration>),
+    message: String,
+    /// set when the user quits, the games being then played without drawing them
+    quit: bool,
+}
+
+/// Play `games` games in a full-screen interface : humans choose their move with the arrow
+/// keys and Enter, or with the mouse. The games are played by `game::play_game`, notifying
+/// every observer. Return the number of wins of X, of O and of draws of the games played
+/// before the user quits.
+pub fn play_games(
+    engine_x: Engine,
+    engine_o: Engine,
+    games: usize,
+    time_control: TimeControl,
+    data: &EngineData,
+    observers: &mut [&mut dyn GameObserver],
+) -> Result<(usize, usize, usize)> {
+    let _guard = TerminalGuard::new()?;
+    let mut tui = Tui {
+        out: io::stdout(),
+        engines: (engine_x, engine_o),
+        board: board::generate_new_board(),
+        player: Player::PlayerX,
+        last_move: None,
+        cursor: (1, 1),
+        games,
+        game: 0,
+        score: (0, 0, 0),
+        time_control,
+        clocks: (None, None),
+        message: String::new(),
+        quit: false,
+    };
+    for _ in 0..games {
+        let mut game_observers: Vec<&mut dyn GameObserver> = vec![&mut tui];
+        for observer in observers.iter_mut() {
+            game_observers.push(&mut **observer);
+        }
+        let played = game::play_game(engine_x, engine_o, time_control, data, &mut game_observers);
+        drop(game_observers);
+        if tui.quit {
+            break;
+        }
+        played?;
+    }
+    Ok(tui.score)
+}
+
+impl GameObserver for Tui {
+    fn on_game_started(&mut self, _engine_x: Engine, _engine_o: Engine, board: &Board) {
+        self.game += 1;
+        self.board = *board;
+        self.player = Player::PlayerX;
+        self.last_move = None;
+        self.clocks = (self.time_control.base, self.time_control.base);
+        self.show_next_player();
+    }
+
+    fn on_move_played(&mut self, event: &MoveEvent) {
+        if self.quit {
+            return;
+        }
+        self.board = event.board;
+        self.last_move = Some(event.engine_move.coord);
+        self.player = game::switch_player(&event.player);
+        match event.player {
+            Player::PlayerX => self.clocks.0 = event.remaining,
+            Player::PlayerO => self.clocks.1 = event.remaining,
+        }
+        if event.engine != Engine::Human {
+            self.message = format!(
+                "{:?} ({:?}) plays {}",
+                event.player,
+                event.engine,
+                board::format_move(event.engine_move.coord)
+            );
+            self.wait(ENGINE_DELAY);
+        }
+        if board::is_move_win(&self.board).is_none() && !board::is_board_full(&self.board) {
+            self.show_next_player();
+        }
+    }
+
+    fn on_game_ended(&mut self, record: &GameRecord, reason: EndReason) {
+        if self.quit {
+            return;
+        }
+        match record.winner {
+            Some(Player::PlayerX) => self.score.0 += 1,
+            Some(Player::PlayerO) => self.score.1 += 1,
+            None => self.score.2 += 1,
+        }
+        self.message = match (reason, record.winner) {
+            (EndReason::Time, Some(winner)) => format!(
+                "{:?} lost on time, well done {:?} !!!",
+                game::switch_player(&winner),
+                winner
+            ),
+            (_, Some(winner)) => format!("Well done {:?} !!!", winner),
+            (_, None) => "This is a draw !".to_owned(),
+        };
+        if self.engines.0 != Engine::Human && self.engines.1 != Engine::Human {
+            self.wait(GAME_END_DELAY);
+            return;
+        }
+        self.message += " Press a key to continue, q to quit.";
+        if let Err(e) = self.wait_key() {
+            error!("Error reading the terminal : {}", e);
+            self.quit = true;
+        }
+    }
+
+    fn human_move(
+        &mut self,
+        board: &Board,
+        player: &Player,
+        deadline: Option<Instant>,
+    ) -> Option<Result<Move>> {
+        if self.quit {
+            return Some(Err(anyhow!("the user quit")));
+        }
+        self.board = *board;
+        self.player = *player;
+        match self.read_move(deadline) {
+            Ok(Some(m)) => Some(Ok(m)),
+            // the game loop ends the game on time
+            Ok(None) => Some(Err(anyhow!("{:?} has no time left", player))),
+            Err(e) => {
+                self.quit = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+impl Tui {
+    fn engine(&self, player: Player) -> Engine {
+        match player {
+            Player::PlayerX => self.engines.0,
+            Player::PlayerO => self.engines.1,
+        }
+    }
+
+    /// Tell who plays next : the human's prompt is written when their move is asked
+    fn show_next_player(&mut self) {
+        let engine = self.engine(self.player);
+        if engine != Engine::Human {
+            self.message = format!("{:?} ({:?}) is thinking...", self.player, engine);
+            self.redraw();
+        }
+    }
+
+    /// Move the cursor until a legal case is chosen, None when the deadline is reached.
+    /// Fail if the user quits.
+    fn read_move(&mut self, deadline: Option<Instant>) -> Result<Option<Move>> {
+        let mut notice = String::new();
+        loop {
+            let timeout = match deadline {
+                Some(d) if Instant::now() >= d => return Ok(None),
+                // redraw every second for the time left
+                Some(d) => Some((d - Instant::now()).min(Duration::from_secs(1))),
+                None => None,
+            };
+            let time_left = match deadline {
+                Some(d) => format!(", {:.0}s left", (d - Instant::now()).as_secs_f64()),
+                None => String::new(),
+            };
+            self.message = format!(
+                "{}{:?} to play{} : arrows and Enter, or click a case. q to quit.",
+                notice, self.player, time_left
+            );
+            self.draw()?;
+            match self.read_input(timeout)? {
+                Input::Play(m) if board::is_valid_move(&self.board, m) => return Ok(Some(m)),
+                Input::Play(m) => notice = format!("{} is already taken. ", board::format_move(m)),
+                Input::Quit => bail!("the user quit"),
+                Input::Nothing => {}
+            }
+        }
+    }
+
+    /// Wait for a key or a click, quitting on q
+    fn wait_key(&mut self) -> Result<()> {
+        loop {
+            self.draw()?;
+            match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => {
+                    self.quit = is_quit_key(key.code, key.modifiers);
+                    return Ok(());
+                }
+                Event::Mouse(mouse) if mouse.kind == MouseEventKind::Down(MouseButton::Left) => {
+                    return Ok(());
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Pause, quitting if the user asks for it
+    fn wait(&mut self, duration: Duration) {
+        match self.pause(duration) {
+            Ok(true) => {}
+            Ok(false) => self.quit = true,
+            Err(e) => {
+                error!("Error reading the terminal : {}", e);
+                self.quit = true;
+            }
+        }
+    }
+
+    /// Draw the screen, the game going on if the terminal fails
+    fn redraw(&mut self) {
+        if let Err(e) = self.draw() {
+            error!("Error drawing the game : {}", e);
+        }
+    }
+
+    /// Read an event, Nothing if none came before the timeout
+    fn read_input(&mut self, timeout: Option<Duration>) -> Result<Input> {
+        if let Some(timeout) = timeout {
+            if !event::poll(timeout)? {
+                return Ok(Input::Nothing);
+            }
+        }
+        match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => {
+                if is_quit_key(key.code, key.modifiers) {
+                    return Ok(Input::Quit);
+                }
+                let (y, x) = self.cursor;
+                match key.code {
+                    KeyCode::Up => self.cursor = (y.saturating_sub(1), x),
+                    KeyCode::Down => self.cursor = ((y + 1).min(2), x),
+                    KeyCode::Left => self.cursor = (y, x.saturating_sub(1)),
+                    KeyCode::Right => self.cursor = (y, (x + 1).min(2)),
+                    KeyCode::Enter | KeyCode::Char(' ') => return Ok(Input::Play(self.cursor)),
+                    _ => {}
+                }
+            }
+            Event::Mouse(mouse) if mouse.kind == MouseEventKind::Down(MouseButton::Left) => {
+                if let Some(m) = case_at(mouse.column, mouse.row) {
+                    self.cursor = m;
+                    return Ok(Input::Play(m));
+                }
+            }
+            _ => {}
+        }
+        Ok(Input::Nothing)
+    }
+
+    /// Wait while redrawing on resize. Return false if the user quits.
+    fn pause(&mut self, duration: Duration) -> Result<bool> {
+        let deadline = Instant::now() + duration;
+        self.draw()?;
+        loop {
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(true);
+            }
+            if event::poll(deadline - now)? {
+                match event::read()? {
+                    Event::Key(key)
+                        if key.kind == KeyEventKind::Press
+                            && is_quit_key(key.code, key.modifiers) =>
+                    {
+                        return Ok(false);
+                    }
+                    Event::Resize(_, _) => self.draw()?,
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    fn draw(&mut self) -> Result<()> {
+        queue!(
+            self.out,
+            Clear(ClearType::All),
+            MoveTo(0, 0),
+            Print(format!(
+                "Tic-tac-toe : X {:?} against O {:?}",
+                self.engines.0, self.engines.1
+            ))
+        )?;
+        let winning_line = board::winning_line(&self.board);
+        let human_to_play = self.engine(self.player) == Engine::Human;
+        for y in 0..3 {
+            for x in 0..3 {
+                let (column, row) = case_position((y, x));
+                let content = format!(" {} ", self.board[y][x].unwrap_or(' '));
+                let mut styled = content.stylize();
+                if winning_line.is_some_and(|line| line.contains(&(y, x))) {
+                    styled = styled.black().on_green();
+                } else if self.last_move == Some((y, x)) {
+                    styled = styled.yellow().bold();
+                }
+                if human_to_play && winning_line.is_none() && self.cursor == (y, x) {
+                    styled = styled.reverse();
+                }
+                queue!(self.out, MoveTo(column, row), PrintStyledContent(styled))?;
+                if x < 2 {
+                    queue!(self.out, Print("│"))?;
+                }
+            }
+            if y < 2 {
+                let (column, row) = case_position((y, 0));
+                queue!(self.out, MoveTo(column, row + 1), Print("───┼───┼───"))?;
+            }
+        }
+        let mut status = format!(
+            "Game {}/{} ; X win {} ; O win {} ; draw {}",
+            self.game, self.games, self.score.0, self.score.1, self.score.2
+        );
+        if let (Some(x), Some(o)) = self.clocks {
+            status += &format!(
+                " ; clocks X {:.1}s O {:.1}s",
+                x.as_secs_f64(),
+                o.as_secs_f64()
+            );
+        }
+        queue!(
+            self.out,
+            MoveTo(0, GRID_TOP + 6),
+            PrintStyledContent(status.reverse()),
+            MoveTo(0, GRID_TOP + 7),
+            Print(&self.message)
+        )?;
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+fn is_quit_key(code: KeyCode, modifiers: KeyModifiers) -> bool {
+    match code {
+        KeyCode::Char('q') | KeyCode::Esc => true,
+        KeyCode::Char('c') => modifiers.contains(KeyModifiers::CONTROL),
+        _ => false,
+    }
+}
+
+/// Screen column and row of the first character of a case
+fn case_position(m: Move) -> (u16, u16) {
+    (
+        GRID_LEFT + CASE_WIDTH * m.1 as u16,
+        GRID_TOP + 2 * m.0 as u16,
+    )
+}
+
+/// Case under a screen column and row, None for separators and outside of the grid
+fn case_at(column: u16, row: u16) -> Option<Move> {
+    let (column, row) = (column.checked_sub(GRID_LEFT)?, row.checked_sub(GRID_TOP)?);
+    let (y, x) = ((row / 2) as usize, (column / CASE_WIDTH) as usize);
+    if row % 2 == 1 || column % CASE_WIDTH == 3 || y > 2 || x > 2 {
+        return None;
+    }
+    Some((y, x))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn init() {
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[test]
+    fn test_case_at() {
+        init();
+        for y in 0..3 {
+            for x in 0..3 {
+                let (column, row) = case_position((y, x));
+                assert_eq!(Some((y, x)), case_at(column, row));
+                assert_eq!(Some((y, x)), case_at(column + 2, row));
+            }
+        }
+        let (column, row) = case_position((0, 0));
+        assert_eq!(None, case_at(column + 3, row));
+        assert_eq!(None, case_at(column, row + 1));
+        assert_eq!(None, case_at(0, 0));
+        assert_eq!(None, case_at(column, row + 6));
+    }
+}